@@ -0,0 +1,49 @@
+//! Times `render_to_buffer` against `render_to_buffer_f32` on a plain
+//! tone and reports the speedup and the largest sample difference.
+//!
+//! `cargo run --release --example f32_speed [seconds]`
+
+use std::{hint::black_box, time::Instant};
+
+use muza::{wave_forms, WaveForm, WaveFormerBuilder};
+
+const RUNS: u32 = 5;
+
+fn main() {
+    let seconds = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(10.0);
+    for (name, waveform) in [
+        ("sin", wave_forms::sin as WaveForm),
+        ("tri", wave_forms::tri),
+    ] {
+        let waveformer = WaveFormerBuilder::new()
+            .frequency(440.0)
+            .duration(seconds)
+            .waveform(waveform)
+            .build();
+        let time = |render: &dyn Fn() -> Vec<f32>| {
+            let start = Instant::now();
+            for _ in 0..RUNS {
+                black_box(render());
+            }
+            start.elapsed().as_secs_f64() / RUNS as f64
+        };
+        let exact = time(&|| waveformer.render_to_buffer().unwrap());
+        let fast = time(&|| waveformer.render_to_buffer_f32().unwrap());
+        let error = waveformer
+            .render_to_buffer()
+            .unwrap()
+            .iter()
+            .zip(&waveformer.render_to_buffer_f32().unwrap())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        println!(
+            "{name}, {seconds} s: f64 {:.1} ms, f32 {:.1} ms, {:.2}x, max error {error:.2e}",
+            exact * 1e3,
+            fast * 1e3,
+            exact / fast
+        );
+    }
+}
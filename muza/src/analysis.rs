@@ -0,0 +1,527 @@
+use std::f64::consts::PI;
+
+/// Lowest and highest pitch [`detect_frequency`] looks for.
+pub const MIN_FREQUENCY: f64 = 20.0;
+pub const MAX_FREQUENCY: f64 = 20_000.0;
+/// Samples inspected by [`detect_frequency`], taken from the middle of the
+/// signal to skip attacks and releases.
+pub const ANALYSIS_WINDOW: usize = 8192;
+/// Frames of the sliding RMS window of [`find_onset`], about 1.3 ms at
+/// the default frame rate.
+pub const ONSET_WINDOW: usize = 64;
+/// Seconds [`frequency_response`] lets each test tone settle, then
+/// measures over.
+pub const RESPONSE_SETTLE: f64 = 0.1;
+pub const RESPONSE_MEASURE: f64 = 0.1;
+
+const YIN_THRESHOLD: f64 = 0.1;
+const REFINE_SPAN: f64 = 0.03;
+/// A multiple of the YIN estimate this much stronger means YIN locked onto
+/// a sub-harmonic, which happens when the period is only a few samples.
+const SUBHARMONIC_RATIO: f64 = 10.0;
+const REFINE_STEPS: usize = 60;
+
+/// Power of `frequency` in `samples` via the Goertzel recurrence.
+pub fn goertzel_power(samples: &[f32], frequency: f64, sample_rate: usize) -> f64 {
+    let coefficient = 2.0 * (2.0 * PI * frequency / sample_rate as f64).cos();
+    let (mut previous, mut before) = (0.0, 0.0);
+    for &sample in samples {
+        let current = sample as f64 + coefficient * previous - before;
+        before = previous;
+        previous = current;
+    }
+    previous * previous + before * before - coefficient * previous * before
+}
+
+fn window(samples: &[f32]) -> &[f32] {
+    if samples.len() <= ANALYSIS_WINDOW {
+        return samples;
+    }
+    let start = (samples.len() - ANALYSIS_WINDOW) / 2;
+    &samples[start..start + ANALYSIS_WINDOW]
+}
+
+/// Vertex of the parabola through `(-1, a)`, `(0, b)`, `(1, c)`.
+fn parabolic_offset(a: f64, b: f64, c: f64) -> f64 {
+    let denominator = a - 2.0 * b + c;
+    if denominator.abs() < f64::EPSILON {
+        0.0
+    } else {
+        0.5 * (a - c) / denominator
+    }
+}
+
+/// Coarse period estimate with the YIN cumulative mean normalized difference.
+fn yin(samples: &[f32], sample_rate: usize) -> Option<f64> {
+    let min_lag = ((sample_rate as f64 / MAX_FREQUENCY).floor() as usize).max(1);
+    let max_lag = ((sample_rate as f64 / MIN_FREQUENCY).ceil() as usize).min(samples.len() / 2);
+    if max_lag <= min_lag + 1 {
+        return None;
+    }
+    let span = samples.len() - max_lag;
+    let mut normalized = vec![1.0; max_lag + 1];
+    let mut running = 0.0;
+    for lag in 1..=max_lag {
+        let difference: f64 = (0..span)
+            .map(|index| (samples[index] - samples[index + lag]) as f64)
+            .map(|delta| delta * delta)
+            .sum();
+        running += difference;
+        normalized[lag] = if running > 0.0 {
+            difference * lag as f64 / running
+        } else {
+            1.0
+        };
+    }
+    let mut lag = (min_lag..max_lag).find(|&lag| normalized[lag] < YIN_THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+    let offset = parabolic_offset(normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+    Some(sample_rate as f64 / (lag as f64 + offset))
+}
+
+/// Fundamental frequency of a mono signal, `None` for silence or noise.
+pub fn detect_frequency(samples: &[f32], sample_rate: usize) -> Option<f64> {
+    let samples = window(samples);
+    let coarse = yin(samples, sample_rate)?;
+    let nyquist = sample_rate as f64 / 2.0;
+    let power = goertzel_power(samples, coarse, sample_rate);
+    let coarse = (2..=4)
+        .map(|multiple| coarse * multiple as f64)
+        .take_while(|&frequency| frequency < nyquist)
+        .map(|frequency| (frequency, goertzel_power(samples, frequency, sample_rate)))
+        .filter(|&(_, multiple)| multiple > power * SUBHARMONIC_RATIO)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(coarse, |(frequency, _)| frequency);
+    let step = 2.0 * REFINE_SPAN * coarse / REFINE_STEPS as f64;
+    let candidate = |index: usize| coarse * (1.0 - REFINE_SPAN) + step * index as f64;
+    let powers: Vec<f64> = (0..=REFINE_STEPS)
+        .map(|index| goertzel_power(samples, candidate(index), sample_rate))
+        .collect();
+    let best = (0..=REFINE_STEPS)
+        .max_by(|&a, &b| powers[a].total_cmp(&powers[b]))
+        .unwrap_or(REFINE_STEPS / 2);
+    if best == 0 || best == REFINE_STEPS {
+        return Some(candidate(best));
+    }
+    let offset = parabolic_offset(powers[best - 1], powers[best], powers[best + 1]);
+    Some(candidate(best) + offset * step)
+}
+
+/// Signed distance from `reference` to `frequency` in cents.
+pub fn cents(frequency: f64, reference: f64) -> f64 {
+    1200.0 * (frequency / reference).log2()
+}
+
+/// In-place radix-2 FFT of `(re, im)` pairs; the length must be a power of two.
+pub fn fft(buffer: &mut [(f64, f64)]) {
+    let length = buffer.len();
+    assert!(
+        length.is_power_of_two(),
+        "FFT length must be a power of two"
+    );
+    let mut swap = 0;
+    for index in 1..length {
+        let mut bit = length >> 1;
+        while swap & bit != 0 {
+            swap ^= bit;
+            bit >>= 1;
+        }
+        swap |= bit;
+        if index < swap {
+            buffer.swap(index, swap);
+        }
+    }
+    let mut size = 2;
+    while size <= length {
+        let angle = -2.0 * PI / size as f64;
+        for start in (0..length).step_by(size) {
+            for offset in 0..size / 2 {
+                let (sin, cos) = (angle * offset as f64).sin_cos();
+                let (re, im) = buffer[start + offset + size / 2];
+                let odd = (re * cos - im * sin, re * sin + im * cos);
+                let even = buffer[start + offset];
+                buffer[start + offset] = (even.0 + odd.0, even.1 + odd.1);
+                buffer[start + offset + size / 2] = (even.0 - odd.0, even.1 - odd.1);
+            }
+        }
+        size *= 2;
+    }
+}
+
+/// Hann-windowed magnitude spectrum of the first `size` samples (zero
+/// padded), `size / 2` bins from DC up to just below Nyquist. Empty unless
+/// `size` is a power of two.
+pub fn magnitude_spectrum(samples: &[f32], size: usize) -> Vec<f64> {
+    if !size.is_power_of_two() {
+        return Vec::new();
+    }
+    let mut buffer: Vec<(f64, f64)> = (0..size)
+        .map(|index| {
+            let window = 0.5 - 0.5 * (2.0 * PI * index as f64 / size as f64).cos();
+            (
+                samples.get(index).copied().unwrap_or(0.0) as f64 * window,
+                0.0,
+            )
+        })
+        .collect();
+    fft(&mut buffer);
+    buffer[..size / 2]
+        .iter()
+        .map(|(re, im)| (re * re + im * im).sqrt())
+        .collect()
+}
+
+/// Short-time magnitude spectra of a mono signal, one column every `hop`
+/// samples; a signal shorter than `size` still yields one column. Empty for
+/// a zero `hop` or a `size` that isn't a power of two.
+pub fn stft(samples: &[f32], size: usize, hop: usize) -> Vec<Vec<f64>> {
+    if hop == 0 || !size.is_power_of_two() {
+        return Vec::new();
+    }
+    let columns = if samples.len() > size {
+        1 + (samples.len() - size) / hop
+    } else {
+        1
+    };
+    (0..columns)
+        .map(|column| magnitude_spectrum(&samples[column * hop..], size))
+        .collect()
+}
+
+/// First frame of the first [`ONSET_WINDOW`]-frame stretch whose RMS over
+/// every channel exceeds `threshold`, moved forward to the first frame in
+/// that stretch with a sample at or above `threshold`. Returns the frame
+/// count when the signal never gets that loud.
+pub fn find_onset(samples: &[f32], channels: usize, threshold: f32) -> usize {
+    let frames: Vec<f64> = samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().map(|&sample| (sample as f64).powi(2)).sum())
+        .collect();
+    let window = ONSET_WINDOW.min(frames.len()).max(1);
+    let limit = (threshold as f64).powi(2) * (window * channels) as f64;
+    let mut energy = 0.0;
+    for (frame, &power) in frames.iter().enumerate() {
+        energy += power;
+        if frame >= window {
+            energy -= frames[frame - window];
+        }
+        if frame + 1 >= window && energy > limit {
+            let start = frame + 1 - window;
+            return samples[start * channels..]
+                .chunks_exact(channels)
+                .position(|frame| frame.iter().any(|sample| sample.abs() >= threshold))
+                .map_or(start, |offset| start + offset);
+        }
+    }
+    frames.len()
+}
+
+/// Level summary of a signal.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub peak: f32,
+    pub rms: f32,
+    /// Samples at or beyond full scale.
+    pub clipped: usize,
+}
+
+impl Stats {
+    pub fn peak_dbfs(&self) -> f64 {
+        20.0 * (self.peak as f64).log10()
+    }
+    pub fn rms_dbfs(&self) -> f64 {
+        20.0 * (self.rms as f64).log10()
+    }
+}
+
+/// Peak, RMS and clipping over every sample of `samples`.
+pub fn analyze(samples: &[f32]) -> Stats {
+    analyze_channels(samples, 1)[0]
+}
+
+/// [`Stats`] of each channel of an interleaved buffer, since one channel
+/// can clip while another doesn't.
+pub fn analyze_channels(samples: &[f32], channels: usize) -> Vec<Stats> {
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    (0..channels)
+        .map(|channel| {
+            let mut stats = Stats::default();
+            let mut energy = 0.0;
+            for &sample in samples.iter().skip(channel).step_by(channels).take(frames) {
+                stats.peak = stats.peak.max(sample.abs());
+                energy += (sample as f64).powi(2);
+                if sample.abs() >= 1.0 {
+                    stats.clipped += 1;
+                }
+            }
+            if frames > 0 {
+                stats.rms = (energy / frames as f64).sqrt() as f32;
+            }
+            stats
+        })
+        .collect()
+}
+
+/// Gain, in dB, of `filter` at each of `freqs`: a unit sine at each
+/// frequency runs through it for [`RESPONSE_SETTLE`] seconds, then the
+/// output's RMS over [`RESPONSE_MEASURE`] seconds is compared with the
+/// input's. The filter keeps its state from one frequency to the next.
+pub fn frequency_response(
+    filter: &mut impl FnMut(f64) -> f64,
+    freqs: &[f64],
+    sample_rate: usize,
+) -> Vec<f32> {
+    let settle = (RESPONSE_SETTLE * sample_rate as f64) as usize;
+    let measure = ((RESPONSE_MEASURE * sample_rate as f64) as usize).max(1);
+    freqs
+        .iter()
+        .map(|&frequency| {
+            let mut energy = 0.0;
+            for frame in 0..settle + measure {
+                let input = (2.0 * PI * frequency * frame as f64 / sample_rate as f64).sin();
+                let output = filter(input);
+                if frame >= settle {
+                    energy += output * output;
+                }
+            }
+            let rms = (energy / measure as f64).sqrt();
+            (20.0 * (rms * std::f64::consts::SQRT_2).log10()) as f32
+        })
+        .collect()
+}
+
+/// Pearson correlation between the channels of an interleaved stereo
+/// buffer: +1 is mono-safe, 0 uncorrelated and -1 cancels when summed to
+/// mono. Gives 0 when either channel is constant, silence included.
+pub fn stereo_correlation(samples: &[f32]) -> f32 {
+    let frames = samples.len() / 2;
+    if frames == 0 {
+        return 0.0;
+    }
+    let (mut left_sum, mut right_sum) = (0.0, 0.0);
+    for frame in samples.chunks_exact(2) {
+        left_sum += frame[0] as f64;
+        right_sum += frame[1] as f64;
+    }
+    let (left_mean, right_mean) = (left_sum / frames as f64, right_sum / frames as f64);
+    let (mut covariance, mut left_power, mut right_power) = (0.0, 0.0, 0.0);
+    for frame in samples.chunks_exact(2) {
+        let left = frame[0] as f64 - left_mean;
+        let right = frame[1] as f64 - right_mean;
+        covariance += left * right;
+        left_power += left * left;
+        right_power += right * right;
+    }
+    let denominator = (left_power * right_power).sqrt();
+    if denominator > 0.0 {
+        (covariance / denominator) as f32
+    } else {
+        0.0
+    }
+}
+
+/// Frames whose sample in any channel jumps from the previous frame's by
+/// more than `threshold`, such as the cuts an abrupt start, stop or splice
+/// leaves. A full-scale sine changes by up to `2π f / sample_rate` per
+/// frame, so keep `threshold` above that for the highest note present.
+pub fn detect_clicks(samples: &[f32], channels: usize, threshold: f32) -> Vec<usize> {
+    samples
+        .chunks_exact(channels)
+        .zip(samples.chunks_exact(channels).skip(1))
+        .enumerate()
+        .filter(|(_, (previous, frame))| {
+            previous
+                .iter()
+                .zip(frame.iter())
+                .any(|(a, b)| (b - a).abs() > threshold)
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// How far two buffers are apart, from [`buffer_diff`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiffReport {
+    /// Largest absolute sample difference.
+    pub max_abs: f32,
+    /// RMS of the sample differences.
+    pub rms: f32,
+    /// Index of the first sample that differs, `None` for equal buffers.
+    /// Buffers of different lengths always diverge, at the end of the
+    /// shorter one at the latest.
+    pub first_divergent: Option<usize>,
+    /// Whether the buffers have different lengths.
+    pub length_mismatch: bool,
+}
+
+/// Compares two buffers sample by sample, for checking that a change to
+/// the render path keeps its output. The shorter buffer counts as padded
+/// with silence for `max_abs` and `rms`, and a NaN on either side makes
+/// both NaN.
+pub fn buffer_diff(a: &[f32], b: &[f32]) -> DiffReport {
+    let length = a.len().max(b.len());
+    let mut report = DiffReport {
+        length_mismatch: a.len() != b.len(),
+        ..Default::default()
+    };
+    let mut sum = 0.0;
+    for index in 0..length {
+        let x = a.get(index).copied().unwrap_or(0.0);
+        let y = b.get(index).copied().unwrap_or(0.0);
+        let difference = (x - y).abs();
+        if x != y && report.first_divergent.is_none() {
+            report.first_divergent = Some(index);
+        }
+        // `f32::max` drops NaN, which would hide a broken render.
+        if difference.is_nan() || difference > report.max_abs {
+            report.max_abs = difference;
+        }
+        sum += (difference as f64).powi(2);
+    }
+    if report.length_mismatch && report.first_divergent.is_none() {
+        report.first_divergent = Some(a.len().min(b.len()));
+    }
+    if length > 0 {
+        report.rms = (sum / length as f64).sqrt() as f32;
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants, WaveFormerBuilder};
+
+    #[test]
+    fn onset_follows_leading_silence() {
+        let samples = WaveFormerBuilder::new()
+            .duration(0.3)
+            .offset(0.2)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let onset = find_onset(&samples, constants::CHANNELS, 0.05);
+        let expected = crate::duration_to_frame(0.2);
+        assert!(onset >= expected && onset < expected + 48, "{onset}");
+        let silence = vec![0.0; 4_800];
+        assert_eq!(find_onset(&silence, 2, 0.05), 2_400);
+    }
+
+    #[test]
+    fn channel_stats_tell_a_hot_left_from_a_quiet_right() {
+        let samples: Vec<f32> = (0..1_000)
+            .flat_map(|frame| {
+                let sign = if frame % 2 == 0 { 1.0 } else { -1.0 };
+                [1.2 * sign, 0.1 * sign]
+            })
+            .collect();
+        let stats = analyze_channels(&samples, 2);
+        assert_eq!(stats.len(), 2);
+        let (left, right) = (stats[0], stats[1]);
+        assert_eq!((left.peak, left.clipped), (1.2, 1_000));
+        assert_eq!((right.peak, right.clipped), (0.1, 0));
+        assert!((left.rms - 1.2).abs() < 1e-6 && (right.rms - 0.1).abs() < 1e-6);
+        assert!(left.peak_dbfs() > 0.0 && right.peak_dbfs() < -19.0);
+        assert_eq!(analyze(&samples).peak, 1.2);
+    }
+
+    #[test]
+    fn low_pass_response_is_flat_below_cutoff_and_falls_above() {
+        let mut biquad =
+            crate::filters::Biquad::low_pass(1_000.0, std::f64::consts::FRAC_1_SQRT_2, 48_000);
+        let response = frequency_response(
+            &mut |sample| biquad.process(sample),
+            &[50.0, 200.0, 1_000.0, 8_000.0],
+            48_000,
+        );
+        assert!(
+            response[0].abs() < 0.1 && response[1].abs() < 0.2,
+            "{response:?}"
+        );
+        assert!((response[2] + 3.0).abs() < 0.3, "{response:?}");
+        assert!(response[3] < -30.0, "{response:?}");
+        let mut identity = |sample| sample;
+        let flat = frequency_response(&mut identity, &[100.0, 10_000.0], 48_000);
+        assert!(flat.iter().all(|gain| gain.abs() < 0.01), "{flat:?}");
+    }
+
+    #[test]
+    fn correlation_spots_mono_inverted_and_independent_channels() {
+        let left = crate::noise::white(48_000, 1);
+        let right = crate::noise::white(48_000, 2);
+        let interleave = |right: &dyn Fn(usize) -> f32| -> Vec<f32> {
+            left.iter()
+                .enumerate()
+                .flat_map(|(index, &sample)| [sample, right(index)])
+                .collect()
+        };
+        assert!((stereo_correlation(&interleave(&|index| left[index])) - 1.0).abs() < 1e-5);
+        assert!((stereo_correlation(&interleave(&|index| -left[index])) + 1.0).abs() < 1e-5);
+        assert!(stereo_correlation(&interleave(&|index| right[index])).abs() < 0.02);
+        assert_eq!(stereo_correlation(&interleave(&|_| 0.0)), 0.0);
+    }
+
+    #[test]
+    fn clicks_flag_a_hard_splice_but_not_a_faded_one() {
+        // Ends a quarter cycle in, at the waveform's peak.
+        let duration = 50.25 / 440.0;
+        let render = |fade: f64| {
+            WaveFormerBuilder::new()
+                .frequency(440.0)
+                .duration(duration)
+                .fade(fade, fade)
+                .build()
+                .render_to_buffer()
+                .unwrap()
+        };
+        let hard = render(0.0);
+        let frames = hard.len() / constants::CHANNELS;
+        let spliced = crate::mix::concatenate(&[hard.clone(), hard], 0.0);
+        assert_eq!(detect_clicks(&spliced, constants::CHANNELS, 0.05), [frames]);
+        let soft = render(0.01);
+        let faded = crate::mix::concatenate(&[soft.clone(), soft], 0.0);
+        assert!(detect_clicks(&faded, constants::CHANNELS, 0.05).is_empty());
+    }
+
+    #[test]
+    fn buffer_diff_finds_a_perturbed_sample() {
+        let original: Vec<f32> = (0..1_000)
+            .map(|index| (index as f32 / 10.0).sin())
+            .collect();
+        assert_eq!(buffer_diff(&original, &original), DiffReport::default());
+        let mut changed = original.clone();
+        changed[417] += 0.25;
+        let report = buffer_diff(&original, &changed);
+        assert_eq!(report.first_divergent, Some(417));
+        assert!((report.max_abs - 0.25).abs() < 1e-6);
+        assert!((report.rms - 0.25 / 1_000f32.sqrt()).abs() < 1e-6);
+        assert!(!report.length_mismatch);
+    }
+
+    #[test]
+    fn buffer_diff_reports_length_mismatch_and_nan() {
+        // Trailing silence matches the padding, so only the length differs.
+        let report = buffer_diff(&[0.5, 0.0, 0.0], &[0.5]);
+        assert!(report.length_mismatch);
+        assert_eq!(report.first_divergent, Some(1));
+        assert_eq!((report.max_abs, report.rms), (0.0, 0.0));
+        let report = buffer_diff(&[0.0, 1.0], &[0.0, f32::NAN]);
+        assert_eq!(report.first_divergent, Some(1));
+        assert!(report.max_abs.is_nan() && report.rms.is_nan());
+        assert!(buffer_diff(&[f32::NAN, 0.0], &[0.0, 2.0]).max_abs.is_nan());
+    }
+
+    #[test]
+    fn spectra_of_unusable_sizes_are_empty() {
+        let samples = vec![0.5; 1_000];
+        assert!(magnitude_spectrum(&samples, 0).is_empty());
+        assert!(magnitude_spectrum(&samples, 100).is_empty());
+        assert_eq!(magnitude_spectrum(&samples, 256).len(), 128);
+        assert!(stft(&samples, 256, 0).is_empty());
+        assert!(stft(&samples, 300, 128).is_empty());
+        assert_eq!(stft(&samples, 256, 128).len(), 6);
+    }
+}
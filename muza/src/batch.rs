@@ -0,0 +1,267 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, Result},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{mix, wav, wave_forms, NoteValue, Ruler, WaveForm, WaveFormerBuilder};
+
+/// Notes rendered per octave directory.
+pub const OCTAVE_NOTES: i64 = 12;
+
+/// Relative output path of a batch file, with `{octave}`, `{note}` (degree
+/// within the octave), `{length}` and `{freq}` (Hz, two decimals) tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathTemplate(String);
+
+impl Default for PathTemplate {
+    fn default() -> Self {
+        Self::new("o[{octave}]/n[{note}]/o[{octave}] n[{note}] l[{length}].wav")
+    }
+}
+
+impl PathTemplate {
+    pub fn new<S: Into<String>>(template: S) -> Self {
+        Self(template.into())
+    }
+    pub fn path(&self, octave: i64, note: i64, length: i64, frequency: f64) -> PathBuf {
+        PathBuf::from(
+            self.0
+                .replace("{octave}", &octave.to_string())
+                .replace("{note}", &note.to_string())
+                .replace("{length}", &length.to_string())
+                .replace("{freq}", &format!("{:.2}", frequency)),
+        )
+    }
+}
+
+/// How [`render_batch`] renders and names every file.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// Path of each file, relative to the batch root.
+    pub template: PathTemplate,
+    pub waveform: WaveForm,
+    /// One file per entry for every note, as rations of [`Ruler::duration`].
+    pub lengths: Vec<i64>,
+}
+
+/// Sines at lengths 1, 2, 4 and 8, named by [`PathTemplate::default`].
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            template: PathTemplate::default(),
+            waveform: wave_forms::sin,
+            lengths: vec![1, 2, 4, 8],
+        }
+    }
+}
+
+/// [`render_batch`] of sines with every other option at its default,
+/// one file per entry of `lengths`.
+pub fn render_octave_batch(
+    ruler: &Ruler,
+    octaves: i64,
+    offset: i64,
+    lengths: &[i64],
+    root: &Path,
+) -> Result<()> {
+    render_batch(
+        ruler,
+        octaves,
+        offset,
+        root,
+        &BatchOptions {
+            lengths: lengths.to_vec(),
+            ..Default::default()
+        },
+    )
+}
+
+/// Renders `octaves` octaves of the ruler, starting `offset` notes below its
+/// reference, one thread per octave, into `root` as `options` describes.
+pub fn render_batch(
+    ruler: &Ruler,
+    octaves: i64,
+    offset: i64,
+    root: &Path,
+    options: &BatchOptions,
+) -> Result<()> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..octaves)
+            .map(|job| scope.spawn(move || render_octave(ruler, job, offset, root, options)))
+            .collect();
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .map_err(|_| Error::other("octave render thread panicked"))?
+        })
+    })
+}
+
+fn render_octave(
+    ruler: &Ruler,
+    job: i64,
+    offset: i64,
+    root: &Path,
+    options: &BatchOptions,
+) -> Result<()> {
+    let start = job * OCTAVE_NOTES - offset;
+    for note in start..start + OCTAVE_NOTES {
+        let abs = note - start;
+        for &length in &options.lengths {
+            let path = root.join(
+                options
+                    .template
+                    .path(job, abs, length, ruler.frequency(note)),
+            );
+            if let Some(directory) = path.parent() {
+                fs::create_dir_all(directory)?;
+            }
+            WaveFormerBuilder::new()
+                .note(ruler, note)
+                .duration(ruler.duration(length as f64))
+                .waveform(options.waveform)
+                .build()
+                .render(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Collects every `.wav` file under `directory`, recursively.
+pub(crate) fn wav_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            wav_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "wav") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The number after `prefix` in a batch file name, e.g. `n[` in
+/// `o[2] n[5] l[1].wav` gives 5.
+pub(crate) fn field(path: &Path, prefix: &str) -> Option<i64> {
+    let name = path.file_name()?.to_str()?;
+    let start = name.find(prefix)? + prefix.len();
+    let end = start + name[start..].find(']')?;
+    name[start..end].parse().ok()
+}
+
+/// Joins a rendered batch under `root` into one ascending scale per
+/// octave, written as `root/octave_N.wav`. Each note contributes its
+/// shortest `l[..]` file, separated by a sixteenth note at the ruler's
+/// tempo. Reads the files already on disk instead of rendering again.
+pub fn stitch_octaves(root: &Path, ruler: &Ruler) -> Result<()> {
+    let mut files = Vec::new();
+    wav_files(root, &mut files)?;
+    let mut octaves: BTreeMap<i64, BTreeMap<i64, (i64, PathBuf)>> = BTreeMap::new();
+    for path in files {
+        let (Some(octave), Some(note), Some(length)) =
+            (field(&path, "o["), field(&path, "n["), field(&path, "l["))
+        else {
+            continue;
+        };
+        let notes = octaves.entry(octave).or_default();
+        if notes
+            .get(&note)
+            .is_none_or(|&(shortest, _)| length < shortest)
+        {
+            notes.insert(note, (length, path));
+        }
+    }
+    let gap = NoteValue::SIXTEENTH.seconds(ruler.bpm);
+    for (octave, notes) in octaves {
+        let mut buffers = Vec::with_capacity(notes.len());
+        for (_, path) in notes.values() {
+            buffers.push(wav::read(path)?.0);
+        }
+        wav::write(
+            root.join(format!("octave_{}.wav", octave)),
+            &mix::concatenate(&buffers, gap),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constants, testing};
+
+    #[test]
+    fn one_octave_batch_writes_every_note_and_length() {
+        let root = testing::temp_path("octave-batch");
+        render_octave_batch(&Ruler::default(), 1, 0, &[1, 2], &root).unwrap();
+        for note in 0..OCTAVE_NOTES {
+            for length in [1, 2] {
+                let path = root.join(format!("o[0]/n[{note}]/o[0] n[{note}] l[{length}].wav"));
+                assert!(path.is_file(), "{}", path.display());
+            }
+        }
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn path_template_substitutes_every_token() {
+        let template = PathTemplate::new("{octave}/{note}_{length} {freq}Hz.wav");
+        assert_eq!(
+            template.path(3, 7, 2, 659.2551),
+            PathBuf::from("3/7_2 659.26Hz.wav")
+        );
+        assert_eq!(
+            PathTemplate::default().path(0, 11, 8, 440.0),
+            PathBuf::from("o[0]/n[11]/o[0] n[11] l[8].wav")
+        );
+    }
+
+    #[test]
+    fn stitching_writes_one_scale_per_octave() {
+        let root = testing::temp_path("stitch");
+        let ruler = Ruler::default();
+        render_octave_batch(&ruler, 2, 12, &[2, 1], &root).unwrap();
+        stitch_octaves(&root, &ruler).unwrap();
+        let note = crate::duration_to_frame(ruler.duration(1.0));
+        let gap = crate::duration_to_frame(NoteValue::SIXTEENTH.seconds(ruler.bpm));
+        for octave in 0..2 {
+            let (samples, _) = wav::read(root.join(format!("octave_{octave}.wav"))).unwrap();
+            let frames = OCTAVE_NOTES as usize * note + (OCTAVE_NOTES as usize - 1) * gap;
+            assert_eq!(samples.len(), frames * constants::CHANNELS);
+        }
+        assert!(!root.join("octave_2.wav").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn batch_waveform_shapes_every_file() {
+        let root = testing::temp_path("saw-batch");
+        let ruler = Ruler::default();
+        let second_harmonic = |waveform| {
+            let options = BatchOptions {
+                waveform,
+                lengths: vec![1],
+                ..Default::default()
+            };
+            render_batch(&ruler, 1, 0, &root, &options).unwrap();
+            let (samples, _) = wav::read(root.join("o[0]/n[0]/o[0] n[0] l[1].wav")).unwrap();
+            let mono = mix::to_mono(&samples, constants::CHANNELS);
+            let power = |k: f64| {
+                crate::analysis::goertzel_power(
+                    &mono,
+                    k * ruler.frequency(0),
+                    constants::FRAME_RATE,
+                )
+            };
+            power(2.0) / power(1.0)
+        };
+        // A saw's harmonics fall as 1/k, so the second has a quarter of the
+        // fundamental's power; a sine has none.
+        assert!((second_harmonic(wave_forms::saw) - 0.25).abs() < 0.02);
+        assert!(second_harmonic(wave_forms::sin) < 1e-6);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+/// Chord qualities, spelled as offsets in degrees of a 12-degree [`crate::Ruler`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chord {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Major7,
+    Minor7,
+    Dominant7,
+}
+
+impl Chord {
+    /// Degree offsets of the chord tones from the root, root first.
+    pub fn intervals(self) -> &'static [i64] {
+        match self {
+            Chord::Major => &[0, 4, 7],
+            Chord::Minor => &[0, 3, 7],
+            Chord::Diminished => &[0, 3, 6],
+            Chord::Augmented => &[0, 4, 8],
+            Chord::Sus2 => &[0, 2, 7],
+            Chord::Sus4 => &[0, 5, 7],
+            Chord::Major7 => &[0, 4, 7, 11],
+            Chord::Minor7 => &[0, 3, 7, 10],
+            Chord::Dominant7 => &[0, 4, 7, 10],
+        }
+    }
+    /// Note indices of the chord built on `root`.
+    pub fn notes(self, root: i64) -> Vec<i64> {
+        self.intervals()
+            .iter()
+            .map(|interval| root + interval)
+            .collect()
+    }
+}
@@ -0,0 +1,292 @@
+//! JSON snapshots of render parameters, available with the `serde` feature.
+//!
+//! Waveforms are function pointers and cannot be serialized, so
+//! [`WaveFormerConfig`] stores them by name and resolves the name back to a
+//! generator when building.
+
+use std::io::{Error, ErrorKind, Result};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    effects::ShapeCurve,
+    envelope::{Adsr, FadeCurve},
+    wav, wave_forms, FrequencyPolicy, NyquistPolicy, WaveFormer, WaveFormerBuilder,
+};
+
+/// Every parameter of a [`WaveFormer`], with the waveform by registry
+/// name. Fields missing from a JSON snapshot take the builder defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveFormerConfig {
+    pub waveform: String,
+    pub duration: f64,
+    pub frequency: f64,
+    pub amplitude: f64,
+    pub sample_rate: usize,
+    pub envelope: Option<Adsr>,
+    pub pitch_envelope: Option<Adsr>,
+    pub pitch_env_depth: f64,
+    pub unison: usize,
+    pub chorus_depth: f64,
+    pub seed: u64,
+    pub waveshape: Option<(ShapeCurve, f32)>,
+    pub phase: f64,
+    pub zero_align: bool,
+    pub nyquist: NyquistPolicy,
+    pub pan: f64,
+    pub auto_pan_rate: f64,
+    pub auto_pan_depth: f64,
+    pub filter_envelope: Option<(Adsr, f64, f64)>,
+    pub fade_in: f64,
+    pub fade_out: f64,
+    pub fade_curve: FadeCurve,
+    pub equal_loudness: bool,
+    pub trim_silence: Option<f32>,
+    pub pad_to: Option<f64>,
+    pub oversample: usize,
+    pub vibrato_rate: f64,
+    pub vibrato_depth: f64,
+    pub tremolo_rate: f64,
+    pub tremolo_depth: f64,
+    pub offset: f64,
+    pub decay: Option<f64>,
+    pub calibration_tone: bool,
+    pub frequency_policy: FrequencyPolicy,
+    pub bit_depth: wav::BitDepth,
+    pub bar: Option<f64>,
+    pub binaural_beat: Option<f64>,
+    pub strict: bool,
+}
+
+impl Default for WaveFormerConfig {
+    fn default() -> Self {
+        Self::from_wave_former(&WaveFormerBuilder::new().build())
+            .expect("the default waveform is registered")
+    }
+}
+
+impl WaveFormerConfig {
+    /// Captures `waveformer`, failing if its waveform has no registered name.
+    pub fn from_wave_former(waveformer: &WaveFormer) -> Result<Self> {
+        let WaveFormer {
+            waveform,
+            duration,
+            frequency,
+            amplitude,
+            sample_rate,
+            envelope,
+            pitch_envelope,
+            pitch_env_depth,
+            unison,
+            chorus_depth,
+            seed,
+            waveshape,
+            phase,
+            zero_align,
+            nyquist,
+            pan,
+            auto_pan_rate,
+            auto_pan_depth,
+            filter_envelope,
+            fade_in,
+            fade_out,
+            fade_curve,
+            equal_loudness,
+            trim_silence,
+            pad_to,
+            oversample,
+            vibrato_rate,
+            vibrato_depth,
+            tremolo_rate,
+            tremolo_depth,
+            offset,
+            decay,
+            calibration_tone,
+            frequency_policy,
+            bit_depth,
+            bar,
+            binaural_beat,
+            strict,
+        } = waveformer;
+        let waveform = wave_forms::name_of(*waveform).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, "waveform has no registered name")
+        })?;
+        Ok(Self {
+            waveform: waveform.to_string(),
+            duration: *duration,
+            frequency: *frequency,
+            amplitude: *amplitude,
+            sample_rate: *sample_rate,
+            envelope: *envelope,
+            pitch_envelope: *pitch_envelope,
+            pitch_env_depth: *pitch_env_depth,
+            unison: *unison,
+            chorus_depth: *chorus_depth,
+            seed: *seed,
+            waveshape: *waveshape,
+            phase: *phase,
+            zero_align: *zero_align,
+            nyquist: *nyquist,
+            pan: *pan,
+            auto_pan_rate: *auto_pan_rate,
+            auto_pan_depth: *auto_pan_depth,
+            filter_envelope: *filter_envelope,
+            fade_in: *fade_in,
+            fade_out: *fade_out,
+            fade_curve: *fade_curve,
+            equal_loudness: *equal_loudness,
+            trim_silence: *trim_silence,
+            pad_to: *pad_to,
+            oversample: *oversample,
+            vibrato_rate: *vibrato_rate,
+            vibrato_depth: *vibrato_depth,
+            tremolo_rate: *tremolo_rate,
+            tremolo_depth: *tremolo_depth,
+            offset: *offset,
+            decay: *decay,
+            calibration_tone: *calibration_tone,
+            frequency_policy: *frequency_policy,
+            bit_depth: *bit_depth,
+            bar: *bar,
+            binaural_beat: *binaural_beat,
+            strict: *strict,
+        })
+    }
+    /// The [`WaveFormer`] this snapshot describes, rejecting unknown
+    /// waveform names and the values [`WaveFormer::validate`] rejects.
+    pub fn build(&self) -> Result<WaveFormer> {
+        let waveform = wave_forms::by_name(&self.waveform).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown waveform {:?}", self.waveform),
+            )
+        })?;
+        let waveformer = WaveFormer {
+            waveform,
+            duration: self.duration,
+            frequency: self.frequency,
+            amplitude: self.amplitude,
+            sample_rate: self.sample_rate,
+            envelope: self.envelope,
+            pitch_envelope: self.pitch_envelope,
+            pitch_env_depth: self.pitch_env_depth,
+            unison: self.unison,
+            chorus_depth: self.chorus_depth,
+            seed: self.seed,
+            waveshape: self.waveshape,
+            phase: self.phase,
+            zero_align: self.zero_align,
+            nyquist: self.nyquist,
+            pan: self.pan,
+            auto_pan_rate: self.auto_pan_rate,
+            auto_pan_depth: self.auto_pan_depth,
+            filter_envelope: self.filter_envelope,
+            fade_in: self.fade_in,
+            fade_out: self.fade_out,
+            fade_curve: self.fade_curve,
+            equal_loudness: self.equal_loudness,
+            trim_silence: self.trim_silence,
+            pad_to: self.pad_to,
+            oversample: self.oversample,
+            vibrato_rate: self.vibrato_rate,
+            vibrato_depth: self.vibrato_depth,
+            tremolo_rate: self.tremolo_rate,
+            tremolo_depth: self.tremolo_depth,
+            offset: self.offset,
+            decay: self.decay,
+            calibration_tone: self.calibration_tone,
+            frequency_policy: self.frequency_policy,
+            bit_depth: self.bit_depth,
+            bar: self.bar,
+            binaural_beat: self.binaural_beat,
+            strict: self.strict,
+        };
+        waveformer.validate()?;
+        Ok(waveformer)
+    }
+}
+
+pub fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(value)?)
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    Ok(serde_json::from_str(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ruler;
+
+    #[test]
+    fn ruler_round_trip_keeps_frequencies() {
+        let ruler = Ruler {
+            frequency: 432.0,
+            bpm: 90.0,
+            ..Default::default()
+        };
+        let restored: Ruler = from_json(&to_json(&ruler).unwrap()).unwrap();
+        for note in -24..24 {
+            assert_eq!(restored.frequency(note), ruler.frequency(note));
+        }
+        assert_eq!(restored.bpm, ruler.bpm);
+    }
+
+    #[test]
+    fn wave_former_round_trip_keeps_every_option() {
+        let waveformer = WaveFormerBuilder::new()
+            .waveform(wave_forms::saw)
+            .frequency(220.0)
+            .duration(0.25)
+            .sample_rate(44_100)
+            .envelope(Adsr::new(0.01, 0.05, 0.6, 0.05))
+            .unison(3, 12.0)
+            .seed(9)
+            .pan(-0.3)
+            .waveshape(ShapeCurve::Tanh, 2.0)
+            .fade(0.01, 0.02)
+            .vibrato(5.0, 20.0)
+            .offset(0.01)
+            .bit_depth(wav::BitDepth::Int24)
+            .build();
+        let config = WaveFormerConfig::from_wave_former(&waveformer).unwrap();
+        let restored: WaveFormerConfig = from_json(&to_json(&config).unwrap()).unwrap();
+        assert_eq!(restored, config);
+        let rebuilt = restored.build().unwrap();
+        assert_eq!(
+            rebuilt.render_to_buffer().unwrap(),
+            waveformer.render_to_buffer().unwrap()
+        );
+        assert_eq!(rebuilt.bit_depth, wav::BitDepth::Int24);
+    }
+
+    #[test]
+    fn missing_fields_take_defaults() {
+        let config: WaveFormerConfig = from_json(r#"{"frequency": 330.0}"#).unwrap();
+        let waveformer = config.build().unwrap();
+        assert_eq!(waveformer.frequency, 330.0);
+        assert_eq!(
+            waveformer.duration,
+            WaveFormerBuilder::new().build().duration
+        );
+    }
+
+    #[test]
+    fn unknown_waveform_is_rejected() {
+        let config = WaveFormerConfig {
+            waveform: "wobble".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.build().unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn ruler_json_is_validated() {
+        let error =
+            from_json::<Ruler>(r#"{"frequency": 440.0, "bpm": 120.0, "rations": []}"#).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("at least one ratio"), "{error}");
+    }
+}
@@ -0,0 +1,7 @@
+pub const FRAME_RATE: usize = 48_000;
+pub const CHANNELS: usize = 2;
+
+/// Settings of a [`crate::WaveFormerBuilder`] left unset.
+pub const DEFAULT_FREQUENCY: f64 = 360.0;
+pub const DEFAULT_DURATION: f64 = 1.0;
+pub const DEFAULT_AMPLITUDE: f64 = 0.5;
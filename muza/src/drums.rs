@@ -0,0 +1,101 @@
+use std::f64::consts::PI;
+
+use crate::{constants, duration_to_frame, envelope, filters::OnePole, frame_to_duration, noise};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrumKind {
+    /// Sine whose pitch falls quickly from a click to a low thump.
+    Kick,
+    /// Short tonal body under a longer burst of high-passed noise.
+    Snare,
+    /// Noise high-passed above the audible body of the kit.
+    HiHat,
+}
+
+const KICK_START: f64 = 160.0;
+const KICK_END: f64 = 45.0;
+const KICK_SWEEP: f64 = 0.035;
+const SNARE_TONE: f64 = 180.0;
+const SNARE_CUTOFF: f64 = 1_000.0;
+const HIHAT_CUTOFF: f64 = 7_000.0;
+
+/// Renders one mono percussive hit of `duration` seconds at the default
+/// frame rate. `seed` drives the noise, so equal seeds give equal hits.
+pub fn percussion(kind: DrumKind, duration: f64, seed: u64) -> Vec<f32> {
+    let frames_count = duration_to_frame(duration);
+    let noise = noise::white(frames_count, seed);
+    let step = 1.0 / constants::FRAME_RATE as f64;
+    let mut samples = Vec::with_capacity(frames_count);
+    match kind {
+        DrumKind::Kick => {
+            let mut phase = 0.0;
+            for frame in 0..frames_count {
+                let seconds = frame_to_duration(frame);
+                let frequency =
+                    KICK_END + (KICK_START - KICK_END) * envelope::exponential(seconds, KICK_SWEEP);
+                let sample =
+                    (2.0 * PI * phase).sin() * envelope::exponential(seconds, duration / 4.0);
+                samples.push((sample * 0.5) as f32);
+                phase = (phase + frequency * step) % 1.0;
+            }
+        }
+        DrumKind::Snare => {
+            let mut filter = OnePole::high_pass(SNARE_CUTOFF, constants::FRAME_RATE);
+            for (frame, &white) in noise.iter().enumerate() {
+                let seconds = frame_to_duration(frame);
+                let tone = (2.0 * PI * SNARE_TONE * seconds).sin()
+                    * envelope::exponential(seconds, duration / 8.0);
+                let rattle =
+                    filter.process(white as f64) * envelope::exponential(seconds, duration / 4.0);
+                samples.push(((tone * 0.4 + rattle * 0.6) * 0.5) as f32);
+            }
+        }
+        DrumKind::HiHat => {
+            let mut first = OnePole::high_pass(HIHAT_CUTOFF, constants::FRAME_RATE);
+            let mut second = OnePole::high_pass(HIHAT_CUTOFF, constants::FRAME_RATE);
+            for (frame, &white) in noise.iter().enumerate() {
+                let seconds = frame_to_duration(frame);
+                let sample = second.process(first.process(white as f64))
+                    * envelope::exponential(seconds, duration / 6.0);
+                samples.push((sample * 0.5) as f32);
+            }
+        }
+    }
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::spectral_centroid;
+
+    const SIZE: usize = 2048;
+
+    #[test]
+    fn kick_centroid_drops_over_time() {
+        let kick = percussion(DrumKind::Kick, 0.5, 1);
+        let early = spectral_centroid(&kick, SIZE, constants::FRAME_RATE);
+        let late = spectral_centroid(&kick[kick.len() / 2..], SIZE, constants::FRAME_RATE);
+        assert!(late < early, "early {early} Hz, late {late} Hz");
+    }
+
+    #[test]
+    fn hihat_energy_is_high() {
+        let hihat = percussion(DrumKind::HiHat, 0.2, 1);
+        assert!(spectral_centroid(&hihat, SIZE, constants::FRAME_RATE) > 5_000.0);
+        let kick = percussion(DrumKind::Kick, 0.2, 1);
+        assert!(spectral_centroid(&kick, SIZE, constants::FRAME_RATE) < 1_000.0);
+    }
+
+    #[test]
+    fn equal_seeds_give_equal_hits() {
+        assert_eq!(
+            percussion(DrumKind::Snare, 0.1, 7),
+            percussion(DrumKind::Snare, 0.1, 7)
+        );
+        assert_ne!(
+            percussion(DrumKind::Snare, 0.1, 7),
+            percussion(DrumKind::Snare, 0.1, 8)
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::{f64::consts::PI, io::Result, path::Path};
+
+use crate::{constants, filters, mix, wav};
+
+pub fn peak(samples: &[f32]) -> f32 {
+    samples
+        .iter()
+        .fold(0.0, |peak, sample| peak.max(sample.abs()))
+}
+
+pub fn apply_gain(samples: &mut [f32], gain: f32) {
+    for sample in samples {
+        *sample *= gain;
+    }
+}
+
+/// Replaces every sample with `f(sample)`.
+pub fn map_samples(samples: &mut [f32], f: impl Fn(f32) -> f32) {
+    for sample in samples {
+        *sample = f(*sample);
+    }
+}
+
+/// Scales the buffer so its peak equals `target`; silence is left alone.
+pub fn normalize(samples: &mut [f32], target: f32) {
+    let peak = peak(samples);
+    if peak > 0.0 {
+        apply_gain(samples, target / peak);
+    }
+}
+
+/// Soft limiter: `ceiling * tanh(x / ceiling)`, never exceeding `ceiling`
+/// and keeping the ordering of sample magnitudes.
+pub fn limit(samples: &mut [f32], ceiling: f32) {
+    for sample in samples {
+        *sample = ceiling * (*sample / ceiling).tanh();
+    }
+}
+
+/// Drops trailing frames in which every channel is below `threshold`.
+pub fn trim_trailing_silence(samples: &mut Vec<f32>, threshold: f32, channels: usize) {
+    let frames = samples
+        .chunks_exact(channels)
+        .rposition(|frame| frame.iter().any(|sample| sample.abs() >= threshold))
+        .map_or(0, |last| last + 1);
+    samples.truncate(frames * channels);
+}
+
+/// Convolves each channel of an interleaved buffer with the same channel
+/// of an interleaved impulse response, e.g. for reverb. The output has
+/// `frames + ir_frames - 1` frames. This is direct time-domain
+/// convolution, fine for short responses; long ones would want FFT
+/// convolution.
+pub fn convolve(samples: &[f32], ir: &[f32], channels: usize) -> Vec<f32> {
+    let frames = samples.len() / channels;
+    let ir_frames = ir.len() / channels;
+    if frames == 0 || ir_frames == 0 {
+        return Vec::new();
+    }
+    let mut out = vec![0.0; (frames + ir_frames - 1) * channels];
+    for frame in 0..frames {
+        for channel in 0..channels {
+            let sample = samples[frame * channels + channel];
+            if sample == 0.0 {
+                continue;
+            }
+            for tap in 0..ir_frames {
+                out[(frame + tap) * channels + channel] += sample * ir[tap * channels + channel];
+            }
+        }
+    }
+    out
+}
+
+/// Wavefolder: scales by `gain`, then reflects anything beyond ±1 back
+/// inward, as many times as needed, so hot signals gain harmonics instead
+/// of flattening. Values within ±1 after the gain pass through unchanged.
+pub fn wavefold(samples: &mut [f32], gain: f32) {
+    for sample in samples {
+        let cycle = (*sample * gain + 1.0) / 4.0;
+        *sample = 1.0 - 4.0 * (cycle - cycle.floor() - 0.5).abs();
+    }
+}
+
+/// Peak follower with separate attack and release times, in seconds, for
+/// the level of a signal as it rises and falls.
+#[derive(Clone, Debug)]
+pub struct EnvelopeFollower {
+    attack: f64,
+    release: f64,
+    level: f64,
+}
+
+impl EnvelopeFollower {
+    pub fn new(attack: f64, release: f64, sample_rate: usize) -> Self {
+        let coefficient = |seconds: f64| {
+            if seconds > 0.0 {
+                (-1.0 / (seconds * sample_rate as f64)).exp()
+            } else {
+                0.0
+            }
+        };
+        Self {
+            attack: coefficient(attack),
+            release: coefficient(release),
+            level: 0.0,
+        }
+    }
+    /// Follows `input`'s magnitude and returns the current level.
+    pub fn process(&mut self, input: f64) -> f64 {
+        let input = input.abs();
+        let coefficient = if input > self.level {
+            self.attack
+        } else {
+            self.release
+        };
+        self.level = filters::flush_denormal(input + coefficient * (self.level - input));
+        self.level
+    }
+}
+
+/// Ducks `target` by the level of `trigger`, both interleaved with
+/// `channels`: each frame's gain is `1 - amount * level`, where `level` is
+/// the trigger's loudest channel through an [`EnvelopeFollower`] and
+/// `amount` is clamped to `[0, 1]`. Past the trigger's end it counts as
+/// silence, so the target recovers.
+pub fn sidechain(
+    target: &mut [f32],
+    trigger: &[f32],
+    channels: usize,
+    amount: f32,
+    attack: f64,
+    release: f64,
+    sample_rate: usize,
+) {
+    let amount = amount.clamp(0.0, 1.0) as f64;
+    let mut follower = EnvelopeFollower::new(attack, release, sample_rate);
+    let mut trigger = trigger.chunks_exact(channels);
+    for frame in target.chunks_exact_mut(channels) {
+        let level = trigger.next().map_or(0.0, peak) as f64;
+        let gain = (1.0 - amount * follower.process(level).min(1.0)) as f32;
+        for sample in frame {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Shortest delay, in milliseconds, the [`flanger`] sweeps down to.
+pub const FLANGER_MIN_DELAY_MS: f64 = 0.1;
+/// Largest feedback magnitude [`flanger`] accepts, keeping it stable.
+pub const FLANGER_MAX_FEEDBACK: f32 = 0.95;
+
+/// Flanger: mixes each channel equally with a copy delayed by
+/// [`FLANGER_MIN_DELAY_MS`] plus up to `depth_ms`, swept by a sine LFO at
+/// `rate` Hz. `feedback` regenerates the delayed signal into the line,
+/// deepening the moving comb notches; it is clamped to
+/// [`FLANGER_MAX_FEEDBACK`]. A `depth_ms` of 0 leaves the buffer untouched.
+pub fn flanger(
+    samples: &mut [f32],
+    channels: usize,
+    rate: f64,
+    depth_ms: f64,
+    feedback: f32,
+    sample_rate: usize,
+) {
+    if depth_ms <= 0.0 {
+        return;
+    }
+    let feedback = feedback.clamp(-FLANGER_MAX_FEEDBACK, FLANGER_MAX_FEEDBACK);
+    let frames_per_ms = sample_rate as f64 / 1000.0;
+    let length = ((FLANGER_MIN_DELAY_MS + depth_ms) * frames_per_ms).ceil() as usize + 2;
+    let mut lines = vec![vec![0.0_f32; length]; channels];
+    for (frame, samples) in samples.chunks_exact_mut(channels).enumerate() {
+        let sweep = (1.0 + (2.0 * PI * rate * frame as f64 / sample_rate as f64).sin()) / 2.0;
+        let delay = (FLANGER_MIN_DELAY_MS + depth_ms * sweep) * frames_per_ms;
+        let whole = delay.floor() as usize;
+        let fraction = (delay - whole as f64) as f32;
+        let write = frame % length;
+        for (sample, line) in samples.iter_mut().zip(&mut lines) {
+            let read = |offset: usize| line[(write + length - offset) % length];
+            let delayed = read(whole) * (1.0 - fraction) + read(whole + 1) * fraction;
+            line[write] = filters::flush_denormal((*sample + feedback * delayed) as f64) as f32;
+            *sample = (*sample + delayed) / 2.0;
+        }
+    }
+}
+
+/// Loads an impulse response WAV as interleaved samples in the crate's
+/// channel layout: mono responses are copied to every channel and other
+/// layouts are mixed down first.
+pub fn load_impulse_response<S: AsRef<Path>>(path: S) -> Result<Vec<f32>> {
+    let (samples, spec) = wav::read(path)?;
+    let channels = spec.channels as usize;
+    if channels == constants::CHANNELS {
+        return Ok(samples);
+    }
+    let mono = if channels == 1 {
+        samples
+    } else {
+        mix::to_mono(&samples, channels)
+    };
+    Ok(mono
+        .iter()
+        .flat_map(|&sample| [sample; constants::CHANNELS])
+        .collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShapeCurve {
+    /// `tanh(x)`, strictly inside `(-1, 1)`.
+    Tanh,
+    /// Flat at `±1` beyond full scale.
+    HardClip,
+    /// `1.5u - 0.5u³` with `u = 2x / 3`: unity slope at zero like the other
+    /// curves, reaching `±1` at `±1.5` and flat beyond it.
+    Cubic,
+}
+
+/// Largest `f32` below one.
+const BELOW_UNITY: f32 = 1.0 - f32::EPSILON / 2.0;
+
+impl ShapeCurve {
+    pub fn apply(self, sample: f32) -> f32 {
+        match self {
+            // f32 `tanh` rounds to exactly ±1 past about 9.
+            ShapeCurve::Tanh => sample.tanh().clamp(-BELOW_UNITY, BELOW_UNITY),
+            ShapeCurve::HardClip => sample.clamp(-1.0, 1.0),
+            ShapeCurve::Cubic => {
+                let sample = (sample / 1.5).clamp(-1.0, 1.0);
+                1.5 * sample - 0.5 * sample.powi(3)
+            }
+        }
+    }
+}
+
+/// Boosts the input by `1 + drive` and passes it through `curve`.
+pub fn waveshape(samples: &mut [f32], drive: f32, curve: ShapeCurve) {
+    let gain = 1.0 + drive;
+    for sample in samples {
+        *sample = curve.apply(*sample * gain);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp() -> Vec<f32> {
+        (-400..=400).map(|index| index as f32 / 100.0).collect()
+    }
+
+    #[test]
+    fn hard_clip_flattens_at_unity() {
+        let mut samples = ramp();
+        waveshape(&mut samples, 1.0, ShapeCurve::HardClip);
+        assert_eq!(samples[0], -1.0);
+        assert_eq!(samples[samples.len() - 1], 1.0);
+        assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+    }
+
+    #[test]
+    fn tanh_stays_strictly_inside_unity() {
+        let mut samples = ramp();
+        waveshape(&mut samples, 4.0, ShapeCurve::Tanh);
+        assert!(samples.iter().all(|sample| sample.abs() < 1.0));
+    }
+
+    #[test]
+    fn zero_drive_is_near_transparent_for_quiet_input() {
+        for curve in [ShapeCurve::Tanh, ShapeCurve::HardClip, ShapeCurve::Cubic] {
+            let input: Vec<f32> = ramp().iter().map(|sample| sample * 0.01).collect();
+            let mut samples = input.clone();
+            waveshape(&mut samples, 0.0, curve);
+            for (shaped, dry) in samples.iter().zip(&input) {
+                assert!((shaped - dry).abs() < 1e-3, "{curve:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn convolving_an_impulse_reproduces_the_response() {
+        let ir = [0.5, -0.5, 0.25, 0.1, -0.125, 0.0];
+        let impulse = [1.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        let out = convolve(&impulse, &ir, 2);
+        assert_eq!(out.len(), (3 + 3 - 1) * 2);
+        assert_eq!(&out[..6], &ir);
+        assert!(out[6..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn convolution_is_commutative() {
+        let a = [0.5, -1.0, 0.25, 0.75];
+        let b = [1.0, 0.5, -0.25];
+        let (ab, ba) = (convolve(&a, &b, 1), convolve(&b, &a, 1));
+        assert_eq!(ab.len(), a.len() + b.len() - 1);
+        for (x, y) in ab.iter().zip(&ba) {
+            assert!((x - y).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn impulse_response_loads_from_wav() {
+        let path = crate::testing::temp_path("ir.wav");
+        let ir = [0.5, 0.5, 0.25, -0.25];
+        crate::wav::write(&path, &ir).unwrap();
+        assert_eq!(load_impulse_response(&path).unwrap(), ir);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn flanger_notch_sweeps_across_a_fixed_tone() {
+        let rate = 48_000;
+        // First notch of the 2.1 ms delay at the top of the sweep.
+        let frequency = 1.0 / (2.0 * (FLANGER_MIN_DELAY_MS + 2.0) / 1000.0);
+        let tone: Vec<f32> = (0..2 * rate)
+            .map(|frame| (2.0 * PI * frequency * frame as f64 / rate as f64).sin() as f32)
+            .collect();
+        let mut samples = tone.clone();
+        flanger(&mut samples, 1, 0.5, 2.0, 0.5, rate);
+        let rms = |center: f64| {
+            let start = ((center - 0.025) * rate as f64) as usize;
+            let window = &samples[start..start + rate / 20];
+            (window.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / window.len() as f64).sqrt()
+        };
+        // Longest delay at 0.5 s, shortest at 1.5 s.
+        assert!(rms(0.5) < 0.3 * rms(1.5), "{} vs {}", rms(0.5), rms(1.5));
+        let mut dry = tone.clone();
+        flanger(&mut dry, 1, 0.5, 0.0, 0.5, rate);
+        assert!(dry == tone);
+    }
+
+    #[test]
+    fn map_samples_applies_the_function_to_every_sample() {
+        let input = ramp();
+        let mut doubled = input.clone();
+        map_samples(&mut doubled, |sample| sample * 2.0);
+        assert!(doubled
+            .iter()
+            .zip(&input)
+            .all(|(out, dry)| *out == dry * 2.0));
+        map_samples(&mut doubled, |sample| sample.clamp(-0.5, 0.5));
+        assert!(doubled.iter().all(|sample| sample.abs() <= 0.5));
+        assert_eq!(doubled[0], -0.5);
+    }
+
+    #[test]
+    fn sidechain_ducks_on_trigger_hits_and_recovers_in_silence() {
+        let rate = 48_000;
+        let frames = rate;
+        let mut target = vec![0.5; frames * 2];
+        // Full-scale hits over 0.0-0.1 s and 0.5-0.6 s.
+        let trigger: Vec<f32> = (0..frames * 6 / 10)
+            .flat_map(|frame| {
+                let hit = (frame % (rate / 2)) < rate / 10;
+                [if hit { 1.0 } else { 0.0 }; 2]
+            })
+            .collect();
+        sidechain(&mut target, &trigger, 2, 0.8, 0.001, 0.05, rate);
+        let level = |seconds: f64| target[(seconds * rate as f64) as usize * 2];
+        for hit in [0.05, 0.55] {
+            assert!((level(hit) - 0.1).abs() < 0.01, "{}", level(hit));
+        }
+        for silence in [0.45, 0.95] {
+            assert!(level(silence) > 0.49, "{}", level(silence));
+        }
+        assert!(level(0.15) > level(0.05) && level(0.15) < level(0.45));
+    }
+
+    #[test]
+    fn wavefold_reflects_hot_signals_and_passes_quiet_ones() {
+        let sine: Vec<f32> = (0..4_800)
+            .map(|frame| (2.0 * PI * 100.0 * frame as f64 / 48_000.0).sin() as f32)
+            .collect();
+        let quiet: Vec<f32> = sine.iter().map(|sample| sample * 0.5).collect();
+        let mut passed = quiet.clone();
+        wavefold(&mut passed, 1.0);
+        assert!(passed
+            .iter()
+            .zip(&quiet)
+            .all(|(out, dry)| (out - dry).abs() < 1e-6));
+        let mut folded = sine.clone();
+        wavefold(&mut folded, 4.0);
+        assert!(folded.iter().all(|sample| sample.abs() <= 1.0 + 1e-6));
+        // Gain 4 reaches 4, folding back through full scale several times.
+        let turns = folded
+            .windows(3)
+            .filter(|w| (w[1] - w[0]) * (w[2] - w[1]) < 0.0)
+            .count();
+        assert!(
+            turns
+                > 4 * sine
+                    .windows(3)
+                    .filter(|w| (w[1] - w[0]) * (w[2] - w[1]) < 0.0)
+                    .count()
+        );
+        let harmonic =
+            |samples: &[f32], k: f64| crate::analysis::goertzel_power(samples, 100.0 * k, 48_000);
+        let overtones = |samples: &[f32]| (2..16).map(|k| harmonic(samples, k as f64)).sum::<f64>();
+        assert!(overtones(&folded) > harmonic(&folded, 1.0));
+        assert!(overtones(&sine) < 1e-6 * harmonic(&sine, 1.0));
+    }
+}
@@ -0,0 +1,136 @@
+/// Attack/decay/sustain/release amplitude envelope, times in seconds.
+///
+/// The release happens inside the note: it starts `release` seconds before
+/// the end of `duration`, so an enveloped note keeps its nominal length.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Adsr {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f64,
+    pub release: f64,
+}
+
+impl Default for Adsr {
+    fn default() -> Self {
+        Self {
+            attack: 0.01,
+            decay: 0.1,
+            sustain: 0.8,
+            release: 0.1,
+        }
+    }
+}
+
+impl Adsr {
+    pub fn new(attack: f64, decay: f64, sustain: f64, release: f64) -> Self {
+        Self {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+    fn held(&self, seconds: f64) -> f64 {
+        if seconds < self.attack {
+            return seconds / self.attack;
+        }
+        let seconds = seconds - self.attack;
+        if seconds < self.decay {
+            return 1.0 - (1.0 - self.sustain) * seconds / self.decay;
+        }
+        self.sustain
+    }
+    /// Envelope level in `[0, 1]` at `seconds` into a note lasting `duration`.
+    pub fn gain(&self, seconds: f64, duration: f64) -> f64 {
+        let release_start = (duration - self.release).max(0.0);
+        if seconds < release_start {
+            return self.held(seconds);
+        }
+        let level = self.held(release_start);
+        if self.release <= 0.0 {
+            return if seconds < duration { level } else { 0.0 };
+        }
+        level * (1.0 - (seconds - release_start) / self.release).max(0.0)
+    }
+}
+
+/// Exponential decay `e^(-seconds / tau)`.
+pub fn exponential(seconds: f64, tau: f64) -> f64 {
+    (-seconds / tau).exp()
+}
+
+/// Shape of a fade, as gain over fade progress `x` in `[0, 1]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FadeCurve {
+    /// `x`.
+    #[default]
+    Linear,
+    /// Linear in decibels over 60 dB, rescaled to start at silence:
+    /// `(10^(3x) - 1) / 999`, about 0.031 at the midpoint.
+    Exponential,
+    /// Raised cosine `(1 - cos(πx)) / 2`, 0.5 at the midpoint.
+    Cosine,
+}
+
+impl FadeCurve {
+    pub fn gain(self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => x,
+            FadeCurve::Exponential => (10.0_f64.powf(3.0 * x) - 1.0) / 999.0,
+            FadeCurve::Cosine => 0.5 - 0.5 * (std::f64::consts::PI * x).cos(),
+        }
+    }
+}
+
+/// Gain at `seconds` into a `duration`-long sound fading in over `fade_in`
+/// and out over `fade_out` seconds.
+pub fn fade(seconds: f64, duration: f64, fade_in: f64, fade_out: f64, curve: FadeCurve) -> f64 {
+    let mut gain = 1.0;
+    if fade_in > 0.0 {
+        gain *= curve.gain(seconds / fade_in);
+    }
+    if fade_out > 0.0 {
+        gain *= curve.gain((duration - seconds) / fade_out);
+    }
+    gain
+}
+
+/// Amplitude window over a whole grain, as gain over progress `x` in
+/// `[0, 1]`; both are silent at the ends and peak at the middle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Window {
+    /// `(1 - cos(2πx)) / 2`.
+    #[default]
+    Hann,
+    /// `1 - |2x - 1|`.
+    Triangular,
+}
+
+impl Window {
+    pub fn gain(self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Window::Hann => 0.5 - 0.5 * (2.0 * std::f64::consts::PI * x).cos(),
+            Window::Triangular => 1.0 - (2.0 * x - 1.0).abs(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fade_curves_differ_at_the_midpoint() {
+        assert!((FadeCurve::Cosine.gain(0.5) - 0.5).abs() < 1e-12);
+        assert!((FadeCurve::Linear.gain(0.5) - 0.5).abs() < 1e-12);
+        assert!((FadeCurve::Exponential.gain(0.5) - 0.031).abs() < 1e-3);
+        for curve in [FadeCurve::Linear, FadeCurve::Exponential, FadeCurve::Cosine] {
+            assert_eq!(curve.gain(0.0), 0.0);
+            assert!((curve.gain(1.0) - 1.0).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::f64::consts::PI;
+
+/// Magnitude below which recursive filter state is flushed to exact zero.
+/// Decaying tails otherwise sink into subnormal floats, which are many
+/// times slower to compute with on common CPUs; 1e-20 is far below audible.
+pub const DENORMAL_FLOOR: f64 = 1e-20;
+
+/// `value`, or 0 when its magnitude is below [`DENORMAL_FLOOR`].
+pub fn flush_denormal(value: f64) -> f64 {
+    if value.abs() < DENORMAL_FLOOR {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// One-pole low-pass or high-pass filter.
+#[derive(Clone, Debug)]
+pub struct OnePole {
+    coefficient: f64,
+    state: f64,
+    high_pass: bool,
+}
+
+impl OnePole {
+    fn new(cutoff: f64, sample_rate: usize, high_pass: bool) -> Self {
+        Self {
+            coefficient: 1.0 - (-2.0 * PI * cutoff / sample_rate as f64).exp(),
+            state: 0.0,
+            high_pass,
+        }
+    }
+    pub fn low_pass(cutoff: f64, sample_rate: usize) -> Self {
+        Self::new(cutoff, sample_rate, false)
+    }
+    pub fn high_pass(cutoff: f64, sample_rate: usize) -> Self {
+        Self::new(cutoff, sample_rate, true)
+    }
+    pub fn process(&mut self, sample: f64) -> f64 {
+        self.state = flush_denormal(self.state + self.coefficient * (sample - self.state));
+        if self.high_pass {
+            sample - self.state
+        } else {
+            self.state
+        }
+    }
+}
+
+/// Bilinear high-shelf coefficients `(b0, b1, b2, a0, a1, a2)` for a
+/// linear shelf gain `high`, in the form BS.1770 specifies its K-weighting
+/// with; `k` is the prewarped `tan(omega / 2)`.
+fn shelf(high: f64, k: f64, q: f64) -> (f64, f64, f64, f64, f64, f64) {
+    let band = high.sqrt();
+    (
+        high + band * k / q + k * k,
+        2.0 * (k * k - high),
+        high - band * k / q + k * k,
+        1.0 + k / q + k * k,
+        2.0 * (k * k - 1.0),
+        1.0 - k / q + k * k,
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BiquadKind {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    HighShelf,
+}
+
+/// Second-order filter with RBJ cookbook coefficients, transposed direct
+/// form II.
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    kind: BiquadKind,
+    q: f64,
+    /// Shelf gain in dB, unused by the other kinds.
+    gain: f64,
+    sample_rate: usize,
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    pub fn new(kind: BiquadKind, frequency: f64, q: f64, sample_rate: usize) -> Self {
+        let mut biquad = Self {
+            kind,
+            q,
+            gain: 0.0,
+            sample_rate,
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        };
+        biquad.retune(frequency);
+        biquad
+    }
+    pub fn low_pass(cutoff: f64, q: f64, sample_rate: usize) -> Self {
+        Self::new(BiquadKind::LowPass, cutoff, q, sample_rate)
+    }
+    pub fn high_pass(cutoff: f64, q: f64, sample_rate: usize) -> Self {
+        Self::new(BiquadKind::HighPass, cutoff, q, sample_rate)
+    }
+    /// Band-pass with 0 dB gain at `center`.
+    pub fn band_pass(center: f64, q: f64, sample_rate: usize) -> Self {
+        Self::new(BiquadKind::BandPass, center, q, sample_rate)
+    }
+    pub fn notch(center: f64, q: f64, sample_rate: usize) -> Self {
+        Self::new(BiquadKind::Notch, center, q, sample_rate)
+    }
+    /// Boosts or cuts everything above `frequency` by `gain` dB.
+    pub fn high_shelf(frequency: f64, q: f64, gain: f64, sample_rate: usize) -> Self {
+        let mut biquad = Self::new(BiquadKind::HighShelf, frequency, q, sample_rate);
+        biquad.gain = gain;
+        biquad.retune(frequency);
+        biquad
+    }
+    /// Moves the cutoff or center frequency, keeping the filter state so
+    /// it can be swept while running.
+    pub fn retune(&mut self, frequency: f64) {
+        let omega = 2.0 * PI * frequency / self.sample_rate as f64;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * self.q);
+        let (a0, a1, a2) = (1.0 + alpha, -2.0 * cos, 1.0 - alpha);
+        let (b0, b1, b2, a0, a1, a2) = match self.kind {
+            BiquadKind::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, a0, a1, a2),
+            BiquadKind::HighPass => (
+                (1.0 + cos) / 2.0,
+                -(1.0 + cos),
+                (1.0 + cos) / 2.0,
+                a0,
+                a1,
+                a2,
+            ),
+            BiquadKind::BandPass => (alpha, 0.0, -alpha, a0, a1, a2),
+            BiquadKind::Notch => (1.0, -2.0 * cos, 1.0, a0, a1, a2),
+            BiquadKind::HighShelf => {
+                shelf(10.0_f64.powf(self.gain / 20.0), (omega / 2.0).tan(), self.q)
+            }
+        };
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+    pub fn process(&mut self, sample: f64) -> f64 {
+        let output = self.b0 * sample + self.z1;
+        self.z1 = self.b1 * sample - self.a1 * output + self.z2;
+        self.z2 = self.b2 * sample - self.a2 * output;
+        // Flushing one state alone feeds a resonant tail a little energy
+        // back and can keep it ringing just above the floor for good.
+        if flush_denormal(self.z1) == 0.0 && flush_denormal(self.z2) == 0.0 {
+            self.z1 = 0.0;
+            self.z2 = 0.0;
+        }
+        output
+    }
+    /// Filters an interleaved buffer with an independent copy of this
+    /// filter per channel.
+    pub fn process_interleaved(&self, samples: &mut [f32], channels: usize) {
+        let mut filters = vec![self.clone(); channels];
+        for frame in samples.chunks_mut(channels) {
+            for (sample, filter) in frame.iter_mut().zip(&mut filters) {
+                *sample = filter.process(*sample as f64) as f32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    /// RMS of `filter`'s steady-state response to a unit sine at `frequency`.
+    fn response(filter: &Biquad, frequency: f64) -> f64 {
+        let mut filter = filter.clone();
+        let output: Vec<f64> = (0..48_000)
+            .map(|frame| {
+                let sample = (2.0 * PI * frequency * frame as f64 / 48_000.0).sin();
+                filter.process(sample)
+            })
+            .collect();
+        let tail = &output[24_000..];
+        (tail.iter().map(|sample| sample * sample).sum::<f64>() / tail.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn band_pass_keeps_its_center_only() {
+        let filter = Biquad::band_pass(1_000.0, 2.0, 48_000);
+        let center = response(&filter, 1_000.0);
+        assert!((center - FRAC_1_SQRT_2).abs() < 0.01);
+        assert!(response(&filter, 100.0) < 0.1 * center);
+        assert!(response(&filter, 10_000.0) < 0.1 * center);
+    }
+
+    #[test]
+    fn notch_removes_its_center_only() {
+        let filter = Biquad::notch(1_000.0, 2.0, 48_000);
+        assert!(response(&filter, 1_000.0) < 0.01);
+        assert!(response(&filter, 100.0) > 0.69);
+        assert!(response(&filter, 10_000.0) > 0.69);
+    }
+
+    #[test]
+    fn interleaved_channels_filter_independently() {
+        let mut samples: Vec<f32> = (0..4_800)
+            .flat_map(|frame| [(frame as f32 * 0.1).sin(), 0.0])
+            .collect();
+        Biquad::low_pass(500.0, FRAC_1_SQRT_2, 48_000).process_interleaved(&mut samples, 2);
+        assert!(samples
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|&sample| sample == 0.0));
+        assert!(samples.iter().step_by(2).any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn decaying_tails_flush_to_exact_zero() {
+        assert_eq!(flush_denormal(DENORMAL_FLOOR / 2.0), 0.0);
+        assert_eq!(flush_denormal(-DENORMAL_FLOOR * 2.0), -DENORMAL_FLOOR * 2.0);
+        let mut biquad = Biquad::low_pass(200.0, 8.0, 48_000);
+        let mut one_pole = OnePole::low_pass(50.0, 48_000);
+        let tail = |process: &mut dyn FnMut(f64) -> f64| {
+            let mut outputs = vec![process(1.0)];
+            outputs.extend((0..10 * 48_000).map(|_| process(0.0)));
+            outputs
+        };
+        for outputs in [
+            tail(&mut |sample| biquad.process(sample)),
+            tail(&mut |sample| one_pole.process(sample)),
+        ] {
+            assert!(outputs.iter().all(|output| !output.is_subnormal()));
+            assert!(outputs[outputs.len() - 48_000..]
+                .iter()
+                .all(|&output| output == 0.0));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Short windowed grains and textures built from them.
+
+use std::io::Result;
+
+use crate::{
+    constants, duration_to_frame, effects, envelope::Window, noise::Rng, WaveForm,
+    WaveFormerBuilder,
+};
+
+/// Length, in seconds, of each grain of [`granular_cloud`].
+pub const CLOUD_GRAIN: f64 = 0.05;
+
+/// Renders a `duration`-second tone shaped by `window` over its whole
+/// length, so the first and last frames are silent.
+pub fn grain(
+    waveform: WaveForm,
+    frequency: f64,
+    duration: f64,
+    window: Window,
+) -> Result<Vec<f32>> {
+    let mut samples = WaveFormerBuilder::new()
+        .waveform(waveform)
+        .frequency(frequency)
+        .duration(duration)
+        .try_build()?
+        .render_to_buffer()?;
+    let last = (samples.len() / constants::CHANNELS)
+        .saturating_sub(1)
+        .max(1);
+    for (index, frame) in samples.chunks_exact_mut(constants::CHANNELS).enumerate() {
+        let gain = window.gain(index as f64 / last as f64) as f32;
+        frame.iter_mut().for_each(|sample| *sample *= gain);
+    }
+    Ok(samples)
+}
+
+/// Scatters `density` Hann grains per second of `waveform` over
+/// `duration` seconds, each [`CLOUD_GRAIN`] long and detuned from
+/// `center_frequency` by a random amount within ±`spread_cents`, then
+/// normalizes the sum to the default amplitude. The same `seed` gives the
+/// same cloud.
+pub fn granular_cloud(
+    waveform: WaveForm,
+    center_frequency: f64,
+    duration: f64,
+    density: f64,
+    spread_cents: f64,
+    seed: u64,
+) -> Result<Vec<f32>> {
+    let mut cloud = vec![0.0; duration_to_frame(duration) * constants::CHANNELS];
+    let grain_frames = duration_to_frame(CLOUD_GRAIN);
+    let latest = duration_to_frame(duration).saturating_sub(grain_frames);
+    let mut rng = Rng::new(seed);
+    for _ in 0..(density.max(0.0) * duration).round() as usize {
+        let start = (rng.next_f64() * latest as f64) as usize * constants::CHANNELS;
+        let cents = spread_cents * rng.bipolar();
+        let frequency = center_frequency * 2.0_f64.powf(cents / 1200.0);
+        let samples = grain(waveform, frequency, CLOUD_GRAIN, Window::Hann)?;
+        for (out, sample) in cloud[start..].iter_mut().zip(samples) {
+            *out += sample;
+        }
+    }
+    effects::normalize(&mut cloud, constants::DEFAULT_AMPLITUDE as f32);
+    Ok(cloud)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windowed_grains_start_and_end_silent_and_peak_in_the_middle() {
+        for window in [Window::Hann, Window::Triangular] {
+            // A square wave's constant magnitude traces the window.
+            let samples = grain(crate::wave_forms::sqr, 100.0, 0.05, window).unwrap();
+            let left: Vec<f32> = samples
+                .iter()
+                .step_by(constants::CHANNELS)
+                .map(|s| s.abs())
+                .collect();
+            assert_eq!(left.len(), 2_400);
+            assert_eq!((left[0], left[left.len() - 1]), (0.0, 0.0));
+            let peak = (0..left.len())
+                .max_by(|&a, &b| left[a].total_cmp(&left[b]))
+                .unwrap();
+            assert!(
+                (1_199..=1_200).contains(&peak),
+                "{window:?} peaks at {peak}"
+            );
+        }
+    }
+
+    /// Local maxima of the 10 ms RMS envelope above a tenth of its peak.
+    fn envelope_peaks(samples: &[f32]) -> usize {
+        let envelope: Vec<f32> = samples
+            .chunks(480 * constants::CHANNELS)
+            .map(|block| (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt())
+            .collect();
+        let floor = envelope.iter().copied().fold(0.0, f32::max) / 10.0;
+        envelope
+            .windows(3)
+            .filter(|w| w[1] > floor && w[1] > w[0] && w[1] >= w[2])
+            .count()
+    }
+
+    #[test]
+    fn denser_clouds_have_more_onsets_and_seeds_reproduce() {
+        let cloud = |density, seed| {
+            granular_cloud(crate::wave_forms::sin, 440.0, 2.0, density, 30.0, seed).unwrap()
+        };
+        let sparse = cloud(5.0, 3);
+        let dense = cloud(40.0, 3);
+        assert_eq!(sparse.len(), dense.len());
+        let (few, many) = (envelope_peaks(&sparse), envelope_peaks(&dense));
+        assert!(few > 0 && many > few, "{few} vs {many} peaks");
+        assert!(cloud(40.0, 3) == dense);
+        assert!(cloud(40.0, 4) != dense);
+    }
+}
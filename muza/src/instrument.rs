@@ -0,0 +1,60 @@
+use crate::{wave_forms, WaveForm};
+
+/// Velocity-layered timbres: each layer takes over from its crossover
+/// velocity upward, so e.g. soft notes can sound sine and loud ones saw.
+#[derive(Clone, Debug)]
+pub struct Instrument {
+    layers: Vec<(f64, WaveForm)>,
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Self::new(wave_forms::sin)
+    }
+}
+
+impl Instrument {
+    /// An instrument playing `waveform` at every velocity.
+    pub fn new(waveform: WaveForm) -> Self {
+        Self {
+            layers: vec![(f64::NEG_INFINITY, waveform)],
+        }
+    }
+    /// Plays `waveform` from `velocity` up to the next layer's crossover.
+    pub fn layer(mut self, velocity: f64, waveform: WaveForm) -> Self {
+        let index = self
+            .layers
+            .partition_point(|&(crossover, _)| crossover <= velocity);
+        self.layers.insert(index, (velocity, waveform));
+        self
+    }
+    pub fn waveform_for(&self, velocity: f64) -> WaveForm {
+        self.layers
+            .iter()
+            .rev()
+            .find(|&&(crossover, _)| crossover <= velocity)
+            .map_or(self.layers[0].1, |&(_, waveform)| waveform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn velocity_picks_the_layer_at_its_crossover() {
+        let instrument = Instrument::new(wave_forms::sin).layer(0.5, wave_forms::saw);
+        assert!(std::ptr::fn_addr_eq(
+            instrument.waveform_for(0.2),
+            wave_forms::sin as WaveForm
+        ));
+        assert!(std::ptr::fn_addr_eq(
+            instrument.waveform_for(0.9),
+            wave_forms::saw as WaveForm
+        ));
+        assert!(std::ptr::fn_addr_eq(
+            instrument.waveform_for(0.5),
+            wave_forms::saw as WaveForm
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+pub mod analysis;
+pub mod batch;
+mod chord;
+#[cfg(feature = "serde")]
+pub mod config;
+pub mod constants;
+pub mod drums;
+pub mod effects;
+pub mod envelope;
+pub mod filters;
+pub mod granular;
+mod instrument;
+pub mod loudness;
+pub mod mix;
+pub mod noise;
+mod notation;
+pub mod pan;
+pub mod resample;
+mod ruler;
+mod sequence;
+mod song;
+#[cfg(feature = "spectrogram")]
+pub mod spectrogram;
+mod streaming;
+pub mod stretch;
+mod synth;
+pub mod tuning;
+pub mod wav;
+mod wave_former;
+pub mod wave_forms;
+
+pub use chord::Chord;
+pub use instrument::Instrument;
+pub use ruler::{IntervalMode, NoteValue, Ruler, TimeSignature};
+pub use sequence::{Event, Sequence};
+pub use song::{Song, Track};
+pub use streaming::StreamingRenderer;
+pub use wave_former::{
+    FrequencyPolicy, NyquistPolicy, WaveFormer, WaveFormerBuilder, CALIBRATION_DURATION,
+    CALIBRATION_FREQUENCY, CALIBRATION_LEVEL, PREVIEW_FADE, RENDER_BLOCK,
+};
+
+pub type WaveForm = fn(x: f64) -> f64;
+
+pub fn duration_to_frame(duration: f64) -> usize {
+    (duration * constants::FRAME_RATE as f64) as usize
+}
+pub fn frame_to_duration(frame: usize) -> f64 {
+    frame as f64 / constants::FRAME_RATE as f64
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::PathBuf;
+
+    use crate::analysis;
+
+    /// A fresh path under the system temp directory, removing anything left
+    /// there by an earlier run.
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("muza-{}-{}", std::process::id(), name));
+        if path.is_dir() {
+            let _ = std::fs::remove_dir_all(&path);
+        } else {
+            let _ = std::fs::remove_file(&path);
+        }
+        path
+    }
+
+    /// Magnitude-weighted mean frequency of the first `size` samples.
+    pub(crate) fn spectral_centroid(samples: &[f32], size: usize, sample_rate: usize) -> f64 {
+        let spectrum = analysis::magnitude_spectrum(samples, size);
+        let bin = sample_rate as f64 / size as f64;
+        let total: f64 = spectrum.iter().sum();
+        spectrum
+            .iter()
+            .enumerate()
+            .map(|(index, magnitude)| index as f64 * bin * magnitude)
+            .sum::<f64>()
+            / total
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::Result;
+
+use crate::{constants, effects, filters::Biquad, WaveForm, WaveFormerBuilder};
+
+/// How far, in dB, A-weighting can hear a tone below 1 kHz before
+/// [`equal_loudness_gain`] stops compensating for it.
+pub const MAX_COMPENSATION_DB: f64 = 12.0;
+
+/// IEC 61672 A-weighting of `frequency` in dB, about 0 at 1 kHz.
+pub fn a_weighting(frequency: f64) -> f64 {
+    let f2 = frequency * frequency;
+    let response = 12194.0_f64.powi(2) * f2 * f2
+        / ((f2 + 20.6_f64.powi(2))
+            * ((f2 + 107.7_f64.powi(2)) * (f2 + 737.9_f64.powi(2))).sqrt()
+            * (f2 + 12194.0_f64.powi(2)));
+    20.0 * response.log10() + 2.0
+}
+
+/// Linear gain in `(0, 1]` that evens out the A-weighting of `frequency`.
+/// Tones heard [`MAX_COMPENSATION_DB`] or more below 1 kHz keep their
+/// amplitude and every other tone is cut by how much louder it is heard,
+/// so a scale sounds roughly even and never exceeds its nominal amplitude.
+/// A 1 kHz tone is cut by [`MAX_COMPENSATION_DB`].
+pub fn equal_loudness_gain(frequency: f64) -> f64 {
+    let db = (-a_weighting(frequency.abs())).min(MAX_COMPENSATION_DB) - MAX_COMPENSATION_DB;
+    10.0_f64.powf(db / 20.0)
+}
+
+/// Length and hop, in seconds, of the gating blocks of
+/// [`integrated_loudness`].
+pub const LOUDNESS_BLOCK: f64 = 0.4;
+pub const LOUDNESS_HOP: f64 = 0.1;
+/// Blocks quieter than this, in LUFS, never count towards the loudness.
+pub const ABSOLUTE_GATE: f64 = -70.0;
+/// Blocks this many LU below the absolutely gated loudness are dropped too.
+pub const RELATIVE_GATE: f64 = 10.0;
+
+/// The BS.1770 K-weighting pre-filter: a +4 dB shelf modelling the head,
+/// then a high-pass at 38 Hz.
+fn k_weighting(sample_rate: usize) -> [Biquad; 2] {
+    [
+        Biquad::high_shelf(1681.97, 0.7072, 3.9998, sample_rate),
+        Biquad::high_pass(38.135, 0.5003, sample_rate),
+    ]
+}
+
+fn block_loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Integrated loudness of an interleaved buffer in LUFS, following ITU-R
+/// BS.1770 with every channel weighted 1, as for stereo. Buffers shorter
+/// than one block are measured whole. Negative infinity for silence and
+/// empty buffers.
+pub fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: usize) -> f64 {
+    let mut squares = vec![0.0; samples.len() / channels];
+    for channel in 0..channels {
+        let mut filters = k_weighting(sample_rate);
+        for (square, frame) in squares.iter_mut().zip(samples.chunks_exact(channels)) {
+            let weighted = filters
+                .iter_mut()
+                .fold(frame[channel] as f64, |sample, filter| {
+                    filter.process(sample)
+                });
+            *square += weighted * weighted;
+        }
+    }
+    if squares.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let block = ((LOUDNESS_BLOCK * sample_rate as f64) as usize).clamp(1, squares.len().max(1));
+    let hop = ((LOUDNESS_HOP * sample_rate as f64) as usize).max(1);
+    let powers: Vec<f64> = (0..=squares.len().saturating_sub(block))
+        .step_by(hop)
+        .map(|start| squares[start..start + block].iter().sum::<f64>() / block as f64)
+        .filter(|&power| block_loudness(power) > ABSOLUTE_GATE)
+        .collect();
+    if powers.is_empty() {
+        return f64::NEG_INFINITY;
+    }
+    let threshold =
+        block_loudness(powers.iter().sum::<f64>() / powers.len() as f64) - RELATIVE_GATE;
+    let gated: Vec<f64> = powers
+        .into_iter()
+        .filter(|&power| block_loudness(power) > threshold)
+        .collect();
+    block_loudness(gated.iter().sum::<f64>() / gated.len() as f64)
+}
+
+/// Scales the buffer to `target` LUFS, leaving silence untouched. Peaks
+/// are not limited, so loud targets can clip.
+pub fn normalize_loudness(samples: &mut [f32], channels: usize, sample_rate: usize, target: f64) {
+    let loudness = integrated_loudness(samples, channels, sample_rate);
+    if loudness.is_finite() {
+        effects::apply_gain(samples, 10.0_f64.powf((target - loudness) / 20.0) as f32);
+    }
+}
+
+/// Renders `a` and `b` as otherwise identical tones, each normalized to
+/// `target_lufs` so level differences don't bias a timbre comparison.
+pub fn render_ab(
+    a: WaveForm,
+    b: WaveForm,
+    frequency: f64,
+    duration: f64,
+    target_lufs: f32,
+) -> Result<(Vec<f32>, Vec<f32>)> {
+    let render = |waveform| -> Result<Vec<f32>> {
+        let waveformer = WaveFormerBuilder::new()
+            .waveform(waveform)
+            .frequency(frequency)
+            .duration(duration)
+            .try_build()?;
+        let mut samples = waveformer.render_to_buffer()?;
+        normalize_loudness(
+            &mut samples,
+            constants::CHANNELS,
+            waveformer.sample_rate,
+            target_lufs as f64,
+        );
+        Ok(samples)
+    };
+    Ok((render(a)?, render(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compensated_peak(frequency: f64) -> f32 {
+        WaveFormerBuilder::new()
+            .frequency(frequency)
+            .amplitude(0.5)
+            .duration(0.2)
+            .equal_loudness(true)
+            .build()
+            .render_to_buffer()
+            .unwrap()
+            .iter()
+            .fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn equal_loudness_favours_low_notes_without_boosting() {
+        let (low, mid) = (compensated_peak(100.0), compensated_peak(1_000.0));
+        assert!(low > 2.0 * mid, "{low} vs {mid}");
+        assert!(low <= 0.5 + 1e-6);
+    }
+
+    #[test]
+    fn equal_loudness_gain_only_cuts() {
+        for frequency in (1..=200).map(|step| step as f64 * 100.0) {
+            let gain = equal_loudness_gain(frequency);
+            assert!(gain > 0.0 && gain <= 1.0, "{frequency} Hz: {gain}");
+        }
+        let db = |frequency| 20.0 * equal_loudness_gain(frequency).log10();
+        assert!((db(1_000.0) + MAX_COMPENSATION_DB).abs() < 0.1);
+        assert!(db(30.0) == 0.0);
+    }
+
+    #[test]
+    fn ab_pair_lands_at_the_same_loudness() {
+        let (a, b) = render_ab(
+            crate::wave_forms::sin,
+            crate::wave_forms::sqr,
+            220.0,
+            1.0,
+            -23.0,
+        )
+        .unwrap();
+        let loudness = |samples: &[f32]| {
+            integrated_loudness(samples, constants::CHANNELS, constants::FRAME_RATE)
+        };
+        assert!((loudness(&a) + 23.0).abs() < 0.1, "{}", loudness(&a));
+        assert!((loudness(&a) - loudness(&b)).abs() < 0.1);
+        assert!(crate::effects::peak(&a) > crate::effects::peak(&b));
+    }
+
+    #[test]
+    fn empty_buffers_measure_as_silence() {
+        let loudness = integrated_loudness(&[], constants::CHANNELS, constants::FRAME_RATE);
+        assert_eq!(loudness, f64::NEG_INFINITY);
+        let mut empty: [f32; 0] = [];
+        normalize_loudness(
+            &mut empty,
+            constants::CHANNELS,
+            constants::FRAME_RATE,
+            -23.0,
+        );
+        let (a, b) = render_ab(
+            crate::wave_forms::sin,
+            crate::wave_forms::saw,
+            220.0,
+            0.0,
+            -23.0,
+        )
+        .unwrap();
+        assert!(a.is_empty() && b.is_empty());
+    }
+}
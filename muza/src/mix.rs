@@ -0,0 +1,266 @@
+//! Mixing several voices onto one bus.
+//!
+//! Signal flow of [`mix_render`]:
+//!
+//! ```text
+//! voice ─┐
+//! voice ─┼─ [per-voice stage] ─ sum ─ [master stage] ─ output
+//! voice ─┘
+//! ```
+//!
+//! The gain → normalize → limit → makeup chain runs exactly once, either on every
+//! voice before the sum ([`BusStage::PerVoice`]) or on the summed bus
+//! ([`BusStage::Master`], the default). Limiting the master keeps the
+//! voices' relative balance, since every voice goes through one shared
+//! transfer curve.
+
+use std::io::Result;
+
+use crate::{constants, duration_to_frame, effects, WaveFormer};
+
+/// Largest side boost of [`widener`]; wider mixes start to fall apart
+/// when summed to mono.
+pub const MAX_WIDEN: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BusStage {
+    #[default]
+    Master,
+    PerVoice,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MixOptions {
+    pub gain: f32,
+    /// Target peak for normalization, skipped when `None`.
+    pub normalize: Option<f32>,
+    /// Soft-limiter ceiling, skipped when `None`, the default. The tanh
+    /// curve bends every sample, not only the peaks, so only set it for
+    /// buses that would otherwise go over.
+    pub limit: Option<f32>,
+    /// Peak to normalize back up to after limiting, skipped when `None`.
+    pub makeup: Option<f32>,
+    pub stage: BusStage,
+}
+
+impl Default for MixOptions {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            normalize: None,
+            limit: None,
+            makeup: None,
+            stage: BusStage::Master,
+        }
+    }
+}
+
+impl MixOptions {
+    /// Limits at `target_peak`, then applies makeup gain so the limited
+    /// bus peaks at `target_peak` again: louder, without overs.
+    pub fn limit_and_normalize(self, target_peak: f32) -> Self {
+        Self {
+            limit: Some(target_peak),
+            makeup: Some(target_peak),
+            ..self
+        }
+    }
+    fn process(&self, samples: &mut [f32]) {
+        effects::apply_gain(samples, self.gain);
+        if let Some(target) = self.normalize {
+            effects::normalize(samples, target);
+        }
+        if let Some(ceiling) = self.limit {
+            effects::limit(samples, ceiling);
+        }
+        if let Some(target) = self.makeup {
+            effects::normalize(samples, target);
+        }
+    }
+}
+
+/// Sums interleaved buffers sample by sample, padding to the longest one.
+pub fn sum(buffers: &[Vec<f32>]) -> Vec<f32> {
+    let length = buffers.iter().map(Vec::len).max().unwrap_or(0);
+    let mut bus = vec![0.0; length];
+    for buffer in buffers {
+        for (out, sample) in bus.iter_mut().zip(buffer) {
+            *out += sample;
+        }
+    }
+    bus
+}
+
+/// Renders every voice and mixes them according to `options`.
+pub fn mix_render(voices: &[WaveFormer], options: &MixOptions) -> Result<Vec<f32>> {
+    let mut buffers = Vec::with_capacity(voices.len());
+    for voice in voices {
+        let mut buffer = voice.render_to_buffer()?;
+        if options.stage == BusStage::PerVoice {
+            options.process(&mut buffer);
+        }
+        buffers.push(buffer);
+    }
+    let mut bus = sum(&buffers);
+    if options.stage == BusStage::Master {
+        options.process(&mut bus);
+    }
+    Ok(bus)
+}
+
+/// Joins interleaved buffers end to end with `gap` seconds of silence
+/// between consecutive buffers.
+pub fn concatenate(buffers: &[Vec<f32>], gap: f64) -> Vec<f32> {
+    let silence = vec![0.0; duration_to_frame(gap) * constants::CHANNELS];
+    let mut joined = Vec::new();
+    for (index, buffer) in buffers.iter().enumerate() {
+        if index > 0 {
+            joined.extend_from_slice(&silence);
+        }
+        joined.extend_from_slice(buffer);
+    }
+    joined
+}
+
+/// Averages every frame's channels into one mono sample.
+pub fn to_mono(samples: &[f32], channels: usize) -> Vec<f32> {
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Duplicates a mono signal onto two interleaved channels.
+pub fn to_stereo(mono: &[f32]) -> Vec<f32> {
+    mono.iter().flat_map(|&sample| [sample, sample]).collect()
+}
+
+/// Mid/side stereo widener for interleaved stereo buffers: scales the side
+/// signal by `1 + amount`, with `amount` clamped to `[0, MAX_WIDEN]`, and
+/// keeps the mid, so the mono sum is untouched apart from clipping to
+/// `[-1, 1]`. Buffers with another channel count are left alone.
+pub fn widener(samples: &mut [f32], amount: f32) {
+    // The mid/side round trip isn't bit-exact, so skip it when there's
+    // nothing to widen.
+    if constants::CHANNELS != 2 || amount <= 0.0 {
+        return;
+    }
+    let width = 1.0 + amount.min(MAX_WIDEN);
+    for frame in samples.chunks_exact_mut(2) {
+        let mid = (frame[0] + frame[1]) / 2.0;
+        let side = (frame[0] - frame[1]) / 2.0 * width;
+        frame[0] = (mid + side).clamp(-1.0, 1.0);
+        frame[1] = (mid - side).clamp(-1.0, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis, WaveFormerBuilder};
+
+    fn voice(frequency: f64) -> WaveFormer {
+        WaveFormerBuilder::new()
+            .frequency(frequency)
+            .amplitude(0.8)
+            .build()
+    }
+
+    #[test]
+    fn master_limit_keeps_balance_within_ceiling() {
+        let options = MixOptions {
+            limit: Some(1.0),
+            ..Default::default()
+        };
+        let bus = mix_render(&[voice(440.0), voice(660.0)], &options).unwrap();
+        assert!(effects::peak(&bus) <= 1.0);
+        let mono = to_mono(&bus, constants::CHANNELS);
+        let low = analysis::goertzel_power(&mono, 440.0, constants::FRAME_RATE);
+        let high = analysis::goertzel_power(&mono, 660.0, constants::FRAME_RATE);
+        assert!((low / high - 1.0).abs() < 0.05, "balance {}", low / high);
+    }
+
+    #[test]
+    fn per_voice_stage_processes_before_the_sum() {
+        let options = MixOptions {
+            normalize: Some(0.25),
+            stage: BusStage::PerVoice,
+            ..Default::default()
+        };
+        let bus = mix_render(&[voice(440.0), voice(660.0)], &options).unwrap();
+        assert!(effects::peak(&bus) <= 0.5 + 1e-6);
+    }
+
+    #[test]
+    fn defaults_leave_the_mix_untouched() {
+        let voice = voice(440.0);
+        let bus = mix_render(std::slice::from_ref(&voice), &MixOptions::default()).unwrap();
+        assert_eq!(bus, voice.render_to_buffer().unwrap());
+    }
+
+    #[test]
+    fn channel_conversions_round_trip() {
+        let mono = [0.5, -0.25, 1.0];
+        let stereo = to_stereo(&mono);
+        assert_eq!(stereo, [0.5, 0.5, -0.25, -0.25, 1.0, 1.0]);
+        assert_eq!(to_mono(&stereo, 2), mono);
+        assert_eq!(to_mono(&[1.0, 0.0, 0.5, -0.5], 2), [0.5, 0.0]);
+    }
+
+    /// Side over mid energy of a stereo buffer.
+    fn side_ratio(samples: &[f32]) -> f64 {
+        let (mut mid, mut side) = (0.0, 0.0);
+        for frame in samples.chunks_exact(2) {
+            mid += ((frame[0] + frame[1]) as f64 / 2.0).powi(2);
+            side += ((frame[0] - frame[1]) as f64 / 2.0).powi(2);
+        }
+        side / mid
+    }
+
+    #[test]
+    fn widener_raises_side_energy_and_keeps_the_mono_sum() {
+        let original: Vec<f32> = (0..4_800)
+            .flat_map(|frame| {
+                let x = frame as f32 / 48.0;
+                [0.4 * x.sin(), 0.3 * (x * 1.1).sin()]
+            })
+            .collect();
+        let mut unchanged = original.clone();
+        widener(&mut unchanged, 0.0);
+        assert_eq!(unchanged, original);
+        let mut previous = side_ratio(&original);
+        for amount in [0.5, 1.0] {
+            let mut wide = original.clone();
+            widener(&mut wide, amount);
+            let ratio = side_ratio(&wide);
+            assert!(ratio > previous);
+            previous = ratio;
+            for (frame, dry) in wide.chunks_exact(2).zip(original.chunks_exact(2)) {
+                assert!(frame.iter().all(|sample| sample.abs() <= 1.0));
+                assert!((frame[0] + frame[1] - dry[0] - dry[1]).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn limit_and_normalize_ends_louder_at_the_target_peak() {
+        let voices = [voice(440.0), voice(550.0)];
+        let rms = |samples: &[f32]| {
+            (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+        };
+        let unlimited = MixOptions {
+            normalize: Some(0.9),
+            ..MixOptions::default()
+        };
+        let plain = mix_render(&voices, &unlimited).unwrap();
+        let loud = mix_render(&voices, &MixOptions::default().limit_and_normalize(0.9)).unwrap();
+        assert!(effects::peak(&mix_render(&voices, &MixOptions::default()).unwrap()) > 1.0);
+        assert!((effects::peak(&loud) - 0.9).abs() < 1e-6);
+        assert!(
+            rms(&loud) > 1.1 * rms(&plain),
+            "{} vs {}",
+            rms(&loud),
+            rms(&plain)
+        );
+    }
+}
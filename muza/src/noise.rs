@@ -0,0 +1,96 @@
+use crate::{constants, duration_to_frame, effects, filters::Biquad};
+
+/// Peak level of [`breath_render`].
+pub const BREATH_LEVEL: f32 = 0.5;
+
+/// Small seeded generator (splitmix64), so noisy renders are reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Uniform value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// Uniform value in `[-1, 1)`.
+    pub fn bipolar(&mut self) -> f64 {
+        2.0 * self.next_f64() - 1.0
+    }
+}
+
+/// `frames` samples of uniform white noise in `[-1, 1)`.
+pub fn white(frames: usize, seed: u64) -> Vec<f32> {
+    let mut rng = Rng::new(seed);
+    (0..frames).map(|_| rng.bipolar() as f32).collect()
+}
+
+/// Mono wind/breath texture: `duration` seconds of seeded white noise
+/// through a band-pass at `frequency` with Q `resonance`, at the default
+/// frame rate. Higher resonance narrows the band towards a whistle; the
+/// result is normalized to [`BREATH_LEVEL`] either way.
+pub fn breath_render(frequency: f64, duration: f64, resonance: f64, seed: u64) -> Vec<f32> {
+    let mut filter = Biquad::band_pass(frequency, resonance, constants::FRAME_RATE);
+    let mut samples: Vec<f32> = white(duration_to_frame(duration), seed)
+        .into_iter()
+        .map(|sample| filter.process(sample as f64) as f32)
+        .collect();
+    effects::normalize(&mut samples, BREATH_LEVEL);
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+
+    /// Mean magnitude spectrum over consecutive 4096-sample windows.
+    fn average_spectrum(samples: &[f32]) -> Vec<f64> {
+        let columns = analysis::stft(samples, 4_096, 4_096);
+        let mut average = vec![0.0; columns[0].len()];
+        for column in &columns {
+            for (sum, magnitude) in average.iter_mut().zip(column) {
+                *sum += magnitude / columns.len() as f64;
+            }
+        }
+        average
+    }
+
+    /// Peak frequency and the width, in Hz, of the bins at least half as
+    /// strong as the peak.
+    fn peak_and_width(samples: &[f32]) -> (f64, f64) {
+        let spectrum = average_spectrum(samples);
+        let bin = constants::FRAME_RATE as f64 / 4_096.0;
+        let peak = (0..spectrum.len())
+            .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+            .unwrap();
+        let wide = spectrum
+            .iter()
+            .filter(|&&magnitude| magnitude >= spectrum[peak] / 2.0)
+            .count();
+        (peak as f64 * bin, wide as f64 * bin)
+    }
+
+    #[test]
+    fn breath_peaks_at_its_frequency_and_narrows_with_resonance() {
+        let (loose_peak, loose_width) = peak_and_width(&breath_render(2_000.0, 2.0, 2.0, 9));
+        let (tight_peak, tight_width) = peak_and_width(&breath_render(2_000.0, 2.0, 20.0, 9));
+        assert!((loose_peak - 2_000.0).abs() < 200.0, "{loose_peak}");
+        assert!((tight_peak - 2_000.0).abs() < 50.0, "{tight_peak}");
+        assert!(
+            tight_width < loose_width / 3.0,
+            "{tight_width} vs {loose_width}"
+        );
+    }
+}
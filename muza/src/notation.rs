@@ -0,0 +1,214 @@
+//! A small text score for sequences, e.g. `C4:q E4:e r:e C4+E4+G4:h.`.
+//!
+//! Tokens play one after another, separated by whitespace. Each is
+//! `notes:length`, where `notes` is `r` for a rest or one or more notes
+//! joined by `+` into a chord. A note is a name and octave, such as `C#4`
+//! or `Bb3` with `A4` as note 0, on 12-degree rulers, or `n` and a note
+//! index, such as `n17`, on any ruler. Lengths are `w`, `h`, `q`, `e` and
+//! `s` for whole to sixteenth notes at the ruler's tempo, with a trailing
+//! `.` for dotted.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{Event, NoteValue, Ruler, Sequence};
+
+const NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+const LENGTHS: [(char, NoteValue); 5] = [
+    ('w', NoteValue::WHOLE),
+    ('h', NoteValue::HALF),
+    ('q', NoteValue::QUARTER),
+    ('e', NoteValue::EIGHTH),
+    ('s', NoteValue::SIXTEENTH),
+];
+/// MIDI number of `A4`, note 0 of a named ruler.
+const A4: i64 = 69;
+/// Times closer than this, in seconds, count as equal.
+const EPSILON: f64 = 1e-6;
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
+
+fn named(ruler: &Ruler) -> bool {
+    ruler.rations.len() == NAMES.len()
+}
+
+fn note_name(ruler: &Ruler, note: i64) -> String {
+    if !named(ruler) {
+        return format!("n{}", note);
+    }
+    let midi = A4 + note;
+    format!(
+        "{}{}",
+        NAMES[midi.rem_euclid(12) as usize],
+        midi.div_euclid(12) - 1
+    )
+}
+
+fn parse_note(ruler: &Ruler, text: &str) -> Result<i64> {
+    if let Some(index) = text.strip_prefix('n') {
+        return index
+            .parse()
+            .map_err(|_| invalid(format!("invalid note index {:?}", text)));
+    }
+    if !named(ruler) {
+        return Err(invalid(format!(
+            "note names need a 12-degree ruler, got {:?}",
+            text
+        )));
+    }
+    let letter = text.get(..1).unwrap_or_default().to_ascii_uppercase();
+    let mut degree = NAMES
+        .iter()
+        .position(|&name| name == letter)
+        .ok_or_else(|| invalid(format!("invalid note name {:?}", text)))?
+        as i64;
+    let mut rest = &text[1..];
+    if let Some(sharp) = rest.strip_prefix('#') {
+        degree += 1;
+        rest = sharp;
+    } else if let Some(flat) = rest.strip_prefix('b') {
+        degree -= 1;
+        rest = flat;
+    }
+    let octave: i64 = rest
+        .parse()
+        .map_err(|_| invalid(format!("invalid octave in {:?}", text)))?;
+    octave
+        .checked_add(1)
+        .and_then(|octave| octave.checked_mul(12))
+        .and_then(|semitones| semitones.checked_add(degree - A4))
+        .ok_or_else(|| invalid(format!("octave out of range in {:?}", text)))
+}
+
+/// The length code nearest to `seconds`.
+fn length_code(ruler: &Ruler, seconds: f64) -> String {
+    LENGTHS
+        .iter()
+        .flat_map(|&(code, value)| {
+            [
+                (code.to_string(), value),
+                (format!("{}.", code), value.dotted()),
+            ]
+        })
+        .min_by(|(_, a), (_, b)| {
+            (a.seconds(ruler.bpm) - seconds)
+                .abs()
+                .total_cmp(&(b.seconds(ruler.bpm) - seconds).abs())
+        })
+        .map(|(code, _)| code)
+        .unwrap_or_default()
+}
+
+fn parse_length(ruler: &Ruler, text: &str) -> Result<f64> {
+    let (code, dotted) = match text.strip_suffix('.') {
+        Some(code) => (code, true),
+        None => (text, false),
+    };
+    let value = LENGTHS
+        .iter()
+        .find(|&&(known, _)| code.len() == 1 && code.starts_with(known))
+        .map(|&(_, value)| value)
+        .ok_or_else(|| invalid(format!("invalid length {:?}", text)))?;
+    let value = if dotted { value.dotted() } else { value };
+    Ok(value.seconds(ruler.bpm))
+}
+
+impl Sequence {
+    /// Reads the text score described in the module docs; every note has
+    /// full velocity.
+    pub fn parse_notation(text: &str, ruler: &Ruler) -> Result<Sequence> {
+        let mut events = Vec::new();
+        let mut time = 0.0;
+        for token in text.split_whitespace() {
+            let (notes, length) = token
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("missing length in {:?}", token)))?;
+            let duration = parse_length(ruler, length)?;
+            if notes != "r" {
+                for note in notes.split('+') {
+                    events.push(Event::new(time, duration, parse_note(ruler, note)?));
+                }
+            }
+            time += duration;
+        }
+        Ok(Sequence::new(events))
+    }
+    /// Writes the sequence as a text score, the inverse of
+    /// [`Sequence::parse_notation`]. Events sharing a start and length
+    /// become chords, gaps become rests and lengths snap to the nearest
+    /// code. Other overlaps can't be written and play one after another,
+    /// and velocities, cents and per-event waveforms and pans are dropped.
+    pub fn to_notation(&self, ruler: &Ruler) -> String {
+        let mut events: Vec<&Event> = self.events.iter().collect();
+        events.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let mut tokens = Vec::new();
+        let mut time = 0.0;
+        let mut index = 0;
+        while index < events.len() {
+            let first = events[index];
+            let chord: Vec<&Event> = events[index..]
+                .iter()
+                .take_while(|event| {
+                    (event.start - first.start).abs() < EPSILON
+                        && (event.duration - first.duration).abs() < EPSILON
+                })
+                .copied()
+                .collect();
+            index += chord.len();
+            if first.start - time > EPSILON {
+                tokens.push(format!("r:{}", length_code(ruler, first.start - time)));
+            }
+            let names: Vec<String> = chord
+                .iter()
+                .map(|event| note_name(ruler, event.note))
+                .collect();
+            tokens.push(format!(
+                "{}:{}",
+                names.join("+"),
+                length_code(ruler, first.duration)
+            ));
+            time = time.max(first.start) + first.duration;
+        }
+        tokens.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(sequence: &Sequence) -> Vec<(i64, f64, f64)> {
+        let mut timings: Vec<_> = sequence
+            .events
+            .iter()
+            .map(|event| (event.note, event.start, event.duration))
+            .collect();
+        timings.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        timings
+    }
+
+    #[test]
+    fn notation_round_trips_through_text() {
+        let ruler = Ruler::default();
+        let text = "C4:q E4:e r:e C4+E4+G4:h. Bb3:s";
+        let parsed = Sequence::parse_notation(text, &ruler).unwrap();
+        let written = parsed.to_notation(&ruler);
+        assert_eq!(written, "C4:q E4:e r:e C4+E4+G4:h. A#3:s");
+        let reparsed = Sequence::parse_notation(&written, &ruler).unwrap();
+        for (a, b) in timings(&parsed).iter().zip(timings(&reparsed)) {
+            assert_eq!(a.0, b.0);
+            assert!((a.1 - b.1).abs() < EPSILON && (a.2 - b.2).abs() < EPSILON);
+        }
+        assert_eq!(reparsed.to_notation(&ruler), written);
+        let odd = Ruler::new(440.0, 120.0, vec![1.0, 1.1, 1.25, 1.4, 1.6, 1.8, 1.9]).unwrap();
+        let indexed = Sequence::parse_notation("n3:q n-2:e", &odd).unwrap();
+        assert_eq!(indexed.to_notation(&odd), "n3:q n-2:e");
+        for huge in ["C9223372036854775807:q", "Cb-9223372036854775808:q"] {
+            let error = Sequence::parse_notation(huge, &ruler).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use std::f64::consts::{FRAC_PI_4, SQRT_2};
+
+/// Equal-power pan law for `pan` in `[-1, 1]` (left to right), scaled so a
+/// centred source keeps unit gain on both channels.
+pub fn equal_power(pan: f64) -> [f64; 2] {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+    [SQRT_2 * angle.cos(), SQRT_2 * angle.sin()]
+}
+
+/// Gain of `channel` for a source at `pan`; panning only applies to stereo.
+pub fn channel_gain(pan: f64, channel: usize, channels: usize) -> f64 {
+    if channels == 2 {
+        equal_power(pan)[channel]
+    } else {
+        1.0
+    }
+}
@@ -0,0 +1,150 @@
+//! Sample-rate conversion for interleaved buffers.
+
+use std::f64::consts::PI;
+
+use crate::filters::Biquad;
+
+/// Second-order low-pass sections [`decimate`] runs before dropping frames.
+pub const DECIMATION_STAGES: usize = 4;
+/// Anti-alias cutoff of [`decimate`] as a fraction of the target Nyquist.
+pub const DECIMATION_CUTOFF: f64 = 0.9;
+
+/// Taps of [`resample`]'s anti-alias filter when no other length is given;
+/// longer filters cut closer to the new Nyquist at more cost.
+pub const DEFAULT_SINC_TAPS: usize = 63;
+
+/// Linearly interpolated conversion from `from_rate` to `to_rate`. Cheap,
+/// but it neither removes content above the new Nyquist nor keeps the top
+/// octave flat; low-pass first when shrinking the rate.
+pub fn linear(samples: &[f32], channels: usize, from_rate: usize, to_rate: usize) -> Vec<f32> {
+    let frames_count = samples.len() / channels;
+    if frames_count == 0 || from_rate == 0 || to_rate == 0 {
+        return Vec::new();
+    }
+    let out_frames = frames_count * to_rate / from_rate;
+    let step = from_rate as f64 / to_rate as f64;
+    let mut out = Vec::with_capacity(out_frames * channels);
+    for frame in 0..out_frames {
+        let position = frame as f64 * step;
+        let index = (position as usize).min(frames_count - 1);
+        let next = (index + 1).min(frames_count - 1);
+        let fraction = (position - index as f64) as f32;
+        for channel in 0..channels {
+            let a = samples[index * channels + channel];
+            let b = samples[next * channels + channel];
+            out.push(a + (b - a) * fraction);
+        }
+    }
+    out
+}
+
+/// Divides the rate of a buffer at `sample_rate` by `factor`: low-passes
+/// below the new Nyquist, then keeps every `factor`-th frame.
+pub fn decimate(samples: &[f32], channels: usize, factor: usize, sample_rate: usize) -> Vec<f32> {
+    if factor <= 1 {
+        return samples.to_vec();
+    }
+    let cutoff = sample_rate as f64 / factor as f64 / 2.0 * DECIMATION_CUTOFF;
+    let mut filtered = samples.to_vec();
+    for _ in 0..DECIMATION_STAGES {
+        Biquad::low_pass(cutoff, std::f64::consts::FRAC_1_SQRT_2, sample_rate)
+            .process_interleaved(&mut filtered, channels);
+    }
+    filtered
+        .chunks_exact(channels)
+        .step_by(factor)
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// Linear-phase FIR low-pass: a Blackman-windowed sinc of `taps` taps
+/// (rounded up to odd) with its cutoff at `cutoff` times the Nyquist
+/// frequency. The output is aligned with the input and as long.
+pub fn sinc_low_pass(samples: &[f32], channels: usize, cutoff: f64, taps: usize) -> Vec<f32> {
+    let taps = taps.max(1) | 1;
+    let half = (taps / 2) as isize;
+    let cutoff = cutoff.clamp(0.0, 1.0);
+    let mut kernel: Vec<f64> = (0..taps)
+        .map(|tap| {
+            let n = tap as isize - half;
+            let sinc = if n == 0 {
+                cutoff
+            } else {
+                (PI * cutoff * n as f64).sin() / (PI * n as f64)
+            };
+            let x = 2.0 * PI * tap as f64 / (taps - 1).max(1) as f64;
+            sinc * (0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos())
+        })
+        .collect();
+    let sum: f64 = kernel.iter().sum();
+    if sum != 0.0 {
+        kernel.iter_mut().for_each(|tap| *tap /= sum);
+    }
+    let frames = (samples.len() / channels) as isize;
+    let mut out = vec![0.0; samples.len()];
+    for frame in 0..frames {
+        for channel in 0..channels {
+            let mut acc = 0.0;
+            for (tap, weight) in kernel.iter().enumerate() {
+                let source = frame + tap as isize - half;
+                if (0..frames).contains(&source) {
+                    acc += weight * samples[source as usize * channels + channel] as f64;
+                }
+            }
+            out[frame as usize * channels + channel] = acc as f32;
+        }
+    }
+    out
+}
+
+/// Converts from `from_rate` to `to_rate`. When the rate drops, content
+/// above the new Nyquist is removed first with a `taps`-tap
+/// [`sinc_low_pass`], so it can't alias into the result the way plain
+/// [`linear`] lets it.
+pub fn resample(
+    samples: &[f32],
+    channels: usize,
+    from_rate: usize,
+    to_rate: usize,
+    taps: usize,
+) -> Vec<f32> {
+    if to_rate < from_rate {
+        let cutoff = to_rate as f64 / from_rate as f64;
+        linear(
+            &sinc_low_pass(samples, channels, cutoff, taps),
+            channels,
+            from_rate,
+            to_rate,
+        )
+    } else {
+        linear(samples, channels, from_rate, to_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+
+    #[test]
+    fn resample_removes_content_above_the_new_nyquist() {
+        let source: Vec<f32> = (0..48_000)
+            .map(|frame| {
+                let t = frame as f64 / 48_000.0;
+                (0.5 * (2.0 * PI * 1_000.0 * t).sin() + 0.5 * (2.0 * PI * 15_000.0 * t).sin())
+                    as f32
+            })
+            .collect();
+        let naive = linear(&source, 1, 48_000, 24_000);
+        let filtered = resample(&source, 1, 48_000, 24_000, DEFAULT_SINC_TAPS);
+        assert_eq!(naive.len(), filtered.len());
+        // 15 kHz folds down to 9 kHz at the new rate.
+        let power = |samples: &[f32], frequency| {
+            analysis::goertzel_power(&samples[1_000..23_000], frequency, 24_000)
+        };
+        assert!(power(&filtered, 9_000.0) < 0.01 * power(&naive, 9_000.0));
+        let kept = power(&filtered, 1_000.0) / power(&naive, 1_000.0);
+        assert!((kept - 1.0).abs() < 0.05, "{kept}");
+    }
+}
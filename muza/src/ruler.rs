@@ -0,0 +1,709 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use crate::{mix, noise::Rng, wav, Chord, Event, Sequence, WaveForm, WaveFormerBuilder};
+
+/// Steps through the scale a [`Ruler::random_melody`] walk can take,
+/// small steps weighted over leaps.
+const MELODY_STEPS: [i64; 7] = [-2, -1, -1, 0, 1, 1, 2];
+
+/// Silence, in seconds, between the notes of [`Ruler::render_scale`].
+pub const SCALE_GAP: f64 = 0.05;
+
+/// How [`Ruler::render_interval`] plays its two notes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntervalMode {
+    /// Both notes at once.
+    #[default]
+    Harmonic,
+    /// The root, then the upper note.
+    Melodic,
+}
+
+/// Beats per bar over the note value of one beat, e.g. 6/8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeSignature {
+    pub beats: u32,
+    pub unit: u32,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self::new(4, 4)
+    }
+}
+
+impl TimeSignature {
+    pub fn new(beats: u32, unit: u32) -> Self {
+        Self { beats, unit }
+    }
+    /// Length of one bar in seconds at `bpm` quarter notes per minute.
+    pub fn bar_seconds(self, bpm: f64) -> f64 {
+        self.beats as f64 * NoteValue(1.0 / self.unit as f64).seconds(bpm)
+    }
+}
+
+/// Rounds `duration` up to a whole number of `bar`s. Durations within a
+/// nanosecond of a bar line stay on it rather than gaining a bar from
+/// rounding noise.
+pub(crate) fn snap(duration: f64, bar: f64) -> f64 {
+    if bar <= 0.0 {
+        return duration;
+    }
+    ((duration / bar) - 1e-9 / bar).ceil().max(0.0) * bar
+}
+
+/// A rhythmic duration as a fraction of a whole note, e.g. 1/4 for a
+/// quarter, for syncing LFO rates to the tempo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteValue(pub f64);
+
+impl NoteValue {
+    pub const WHOLE: NoteValue = NoteValue(1.0);
+    pub const HALF: NoteValue = NoteValue(0.5);
+    pub const QUARTER: NoteValue = NoteValue(0.25);
+    pub const EIGHTH: NoteValue = NoteValue(0.125);
+    pub const SIXTEENTH: NoteValue = NoteValue(0.0625);
+    /// Half as long again, as with a dotted note.
+    pub fn dotted(self) -> Self {
+        NoteValue(self.0 * 1.5)
+    }
+    /// Two thirds as long, three fitting where two would.
+    pub fn triplet(self) -> Self {
+        NoteValue(self.0 * 2.0 / 3.0)
+    }
+    /// Length in seconds at `bpm` quarter notes per minute.
+    pub fn seconds(self, bpm: f64) -> f64 {
+        self.0 * 4.0 * 60.0 / bpm
+    }
+    /// Rate, in Hz, of one cycle per note value at `bpm`; a quarter at
+    /// 120 BPM is 2 Hz.
+    pub fn rate(self, bpm: f64) -> f64 {
+        1.0 / self.seconds(bpm)
+    }
+}
+
+/// A tuning: reference `frequency` for note 0, tempo, and the ratios of
+/// one octave's degrees.
+///
+/// Note `n` plays degree `n.rem_euclid(len)` of octave `n.div_euclid(len)`,
+/// where `len = rations.len()`, so
+/// `frequency(n) = frequency * rations[degree] * 2^octave`. Negative notes
+/// count down the same way: note -1 is the top degree of octave -1. With
+/// ratios strictly increasing inside `[1, 2)`, frequencies rise with the
+/// note index and `frequency(len - 1)` is always just below
+/// `frequency(len) = 2 * frequency`, whatever the table length.
+///
+/// [`Ruler::ration`] and everything built on it panic on an empty table;
+/// [`Ruler::new`] and deserialization reject one through
+/// [`Ruler::validate`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "RulerFields"))]
+pub struct Ruler {
+    pub frequency: f64,
+    pub bpm: f64,
+    pub rations: Vec<f64>,
+}
+
+/// Unchecked [`Ruler`] fields, validated on the way in.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RulerFields {
+    frequency: f64,
+    bpm: f64,
+    rations: Vec<f64>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RulerFields> for Ruler {
+    type Error = Error;
+
+    fn try_from(fields: RulerFields) -> Result<Self> {
+        Ruler::new(fields.frequency, fields.bpm, fields.rations)
+    }
+}
+
+impl Default for Ruler {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            bpm: 120.0,
+            rations: vec![
+                1.0,            // 0
+                256.0 / 243.0,  // 1
+                9.0 / 8.0,      // 2
+                32.0 / 27.0,    // 3
+                81.0 / 64.0,    // 4
+                4.0 / 3.0,      // 5
+                2.0_f64.sqrt(), // 6
+                3.0 / 2.0,      // 7
+                128.0 / 81.0,   // 8
+                27.0 / 16.0,    // 9
+                16.0 / 9.0,     // 10
+                243.0 / 128.0,  // 11
+            ],
+        }
+    }
+}
+
+impl Ruler {
+    /// A ruler that passes [`Ruler::validate`].
+    pub fn new(frequency: f64, bpm: f64, rations: Vec<f64>) -> Result<Ruler> {
+        let ruler = Ruler {
+            frequency,
+            bpm,
+            rations,
+        };
+        ruler.validate()?;
+        Ok(ruler)
+    }
+    /// Rejects an empty ratio table, non-positive or non-finite ratios, and
+    /// a non-positive or non-finite reference frequency or tempo.
+    pub fn validate(&self) -> Result<()> {
+        positive("frequency", self.frequency)?;
+        positive("bpm", self.bpm)?;
+        if self.rations.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "a ruler needs at least one ratio",
+            ));
+        }
+        for &ration in &self.rations {
+            positive("ratio", ration)?;
+        }
+        Ok(())
+    }
+    pub fn ration(&self, note: i64) -> f64 {
+        self.rations[note.rem_euclid(self.rations.len() as i64) as usize]
+    }
+    pub fn power(&self, note: i64) -> f64 {
+        2.0_f64.powi(note.div_euclid(self.rations.len() as i64) as i32)
+    }
+    pub fn frequency(&self, note: i64) -> f64 {
+        self.frequency * self.ration(note) * self.power(note)
+    }
+    /// Loads a Scala `.scl` tuning: `reference` Hz for note 0 and the
+    /// degrees from the file, each given in cents (with a `.`) or as a
+    /// ratio like `3/2` or `2`. The last degree closes the period and must
+    /// be the 2/1 octave, which the ruler supplies itself.
+    pub fn from_scala<S: AsRef<Path>>(path: S, reference: f64, bpm: f64) -> Result<Ruler> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |message: String| Error::new(ErrorKind::InvalidData, message);
+        let mut lines = text.lines().filter(|line| !line.starts_with('!'));
+        lines
+            .next()
+            .ok_or_else(|| invalid("missing description line".into()))?;
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next()?.parse().ok())
+            .ok_or_else(|| invalid("missing note count".into()))?;
+        let mut pitches = Vec::with_capacity(count);
+        for line in lines.take(count) {
+            let value = line.split_whitespace().next().unwrap_or_default();
+            let ratio = if value.contains('.') {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .map(|cents| 2.0_f64.powf(cents / 1200.0))
+            } else {
+                match value.split_once('/') {
+                    Some((numerator, denominator)) => numerator
+                        .parse::<f64>()
+                        .ok()
+                        .zip(denominator.parse::<f64>().ok())
+                        .map(|(numerator, denominator)| numerator / denominator),
+                    None => value.parse::<f64>().ok(),
+                }
+            };
+            match ratio {
+                Some(ratio) if ratio.is_finite() && ratio > 0.0 => pitches.push(ratio),
+                _ => return Err(invalid(format!("invalid pitch {:?}", value))),
+            }
+        }
+        if pitches.len() != count {
+            return Err(invalid(format!(
+                "expected {} pitches, found {}",
+                count,
+                pitches.len()
+            )));
+        }
+        match pitches.pop() {
+            Some(period) if (period - 2.0).abs() < 1e-9 => {}
+            _ => {
+                return Err(invalid(
+                    "only scales repeating at the 2/1 octave are supported".into(),
+                ))
+            }
+        }
+        let mut rations = vec![1.0];
+        rations.extend(pitches);
+        Ruler::new(reference, bpm, rations)
+    }
+    /// `(note, frequency)` for `count` consecutive notes from `start`.
+    pub fn frequency_table(&self, start: i64, count: i64) -> Vec<(i64, f64)> {
+        (start..start + count.max(0))
+            .map(|note| (note, self.frequency(note)))
+            .collect()
+    }
+    /// [`Ruler::frequency_table`] as aligned text, one note per line with
+    /// its ratio to the previous note, so jumps in the tuning stand out.
+    pub fn format_frequency_table(&self, start: i64, count: i64) -> String {
+        let mut text = String::new();
+        let mut previous: Option<f64> = None;
+        for (note, frequency) in self.frequency_table(start, count) {
+            let step = previous.map_or(String::new(), |previous| {
+                format!("{:.6}", frequency / previous)
+            });
+            text.push_str(&format!(
+                "{:>6} {:>12.4} Hz {:>10}\n",
+                note, frequency, step
+            ));
+            previous = Some(frequency);
+        }
+        text
+    }
+    /// LFO rate, in Hz, of one cycle per `value` at this ruler's tempo, for
+    /// vibrato, tremolo or auto-pan.
+    pub fn rate(&self, value: NoteValue) -> f64 {
+        value.rate(self.bpm)
+    }
+    /// Renders `root` and `root + interval` as a half note each at the
+    /// ruler's tempo, together or one after the other, for ear training.
+    pub fn render_interval<S: AsRef<Path>>(
+        &self,
+        root: i64,
+        interval: i64,
+        mode: IntervalMode,
+        path: S,
+    ) -> Result<()> {
+        let duration = NoteValue::HALF.seconds(self.bpm);
+        let mut notes = Vec::with_capacity(2);
+        for note in [root, root + interval] {
+            let waveformer = WaveFormerBuilder::new()
+                .note(self, note)
+                .duration(duration)
+                .build();
+            notes.push(waveformer.render_to_buffer()?);
+        }
+        let samples = match mode {
+            IntervalMode::Harmonic => mix::sum(&notes),
+            IntervalMode::Melodic => mix::concatenate(&notes, SCALE_GAP),
+        };
+        wav::write(path, &samples)
+    }
+    /// Repeats `note` in a rhythm such as `"x.x.xx.x"`: each character is
+    /// one `step` seconds long, `x` a hit lasting the step and anything
+    /// else a rest. Hits play `waveform` whatever the instrument.
+    pub fn pattern(&self, note: i64, pattern: &str, step: f64, waveform: WaveForm) -> Sequence {
+        let events = pattern
+            .chars()
+            .enumerate()
+            .filter(|&(_, symbol)| symbol.eq_ignore_ascii_case(&'x'))
+            .map(|(index, _)| Event::new(index as f64 * step, step, note).waveform(waveform))
+            .collect();
+        Sequence::new(events)
+    }
+    /// Seeded melody of `length` back-to-back notes drawn from `scale`
+    /// (note indices, lowest first). Each note is a short random step
+    /// through the scale from the previous one, at most two degrees and
+    /// bouncing off its ends, so the line moves rather than jumps.
+    pub fn random_melody(
+        &self,
+        scale: &[i64],
+        length: usize,
+        note_duration: f64,
+        seed: u64,
+    ) -> Sequence {
+        if scale.is_empty() {
+            return Sequence::default();
+        }
+        let mut rng = Rng::new(seed);
+        let top = scale.len() as i64 - 1;
+        let mut degree = (rng.next_u64() % scale.len() as u64) as i64;
+        let mut events = Vec::with_capacity(length);
+        for index in 0..length {
+            events.push(Event::new(
+                index as f64 * note_duration,
+                note_duration,
+                scale[degree as usize],
+            ));
+            let step = MELODY_STEPS[(rng.next_u64() % MELODY_STEPS.len() as u64) as usize];
+            degree += step;
+            if degree < 0 || degree > top {
+                degree -= 2 * step;
+            }
+            degree = degree.clamp(0, top);
+        }
+        Sequence::new(events)
+    }
+    /// Blends toward `other` by `t` in `[0, 1]`: every ratio and the
+    /// reference frequency move evenly in cents, the tempo linearly. Fails
+    /// when the rulers have different numbers of degrees.
+    pub fn lerp(&self, other: &Ruler, t: f64) -> Result<Ruler> {
+        if self.rations.len() != other.rations.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "cannot blend a {}-degree ruler with a {}-degree one",
+                    self.rations.len(),
+                    other.rations.len()
+                ),
+            ));
+        }
+        let blend = |a: f64, b: f64| a * (b / a).powf(t);
+        Ok(Ruler {
+            frequency: blend(self.frequency, other.frequency),
+            bpm: self.bpm + (other.bpm - self.bpm) * t,
+            rations: self
+                .rations
+                .iter()
+                .zip(&other.rations)
+                .map(|(&a, &b)| blend(a, b))
+                .collect(),
+        })
+    }
+    /// Rounds `duration` up to the next bar line of `signature` at this
+    /// ruler's tempo: 1.7 s at 120 BPM in 4/4 becomes 2.0 s.
+    pub fn snap_to_bar(&self, duration: f64, signature: TimeSignature) -> f64 {
+        snap(duration, signature.bar_seconds(self.bpm))
+    }
+    /// Sets the tempo, rejecting non-positive or non-finite `bpm`.
+    pub fn with_bpm(self, bpm: f64) -> Result<Self> {
+        Ok(Self {
+            bpm: positive("bpm", bpm)?,
+            ..self
+        })
+    }
+    /// Sets the reference frequency, rejecting non-positive or non-finite
+    /// values.
+    pub fn with_frequency(self, frequency: f64) -> Result<Self> {
+        Ok(Self {
+            frequency: positive("frequency", frequency)?,
+            ..self
+        })
+    }
+    /// Shifts the reference frequency by `cents`, e.g. +7.85 for A442.
+    pub fn with_detune_cents(self, cents: f64) -> Self {
+        Self {
+            frequency: self.frequency * 2.0_f64.powf(cents / 1200.0),
+            ..self
+        }
+    }
+    pub fn duration(&self, ration: f64) -> f64 {
+        self.bpm / 60.0 * ration
+    }
+    /// Renders `count` consecutive notes from `start`, each lasting
+    /// `note_duration` seconds and separated by [`SCALE_GAP`], into one file.
+    pub fn render_scale<S: AsRef<Path>>(
+        &self,
+        waveform: WaveForm,
+        note_duration: f64,
+        start: i64,
+        count: i64,
+        path: S,
+    ) -> Result<()> {
+        let mut notes = Vec::with_capacity(count.max(0) as usize);
+        for note in start..start + count {
+            let waveformer = WaveFormerBuilder::new()
+                .waveform(waveform)
+                .frequency(self.frequency(note))
+                .duration(note_duration)
+                .build();
+            notes.push(waveformer.render_to_buffer()?);
+        }
+        wav::write(path, &mix::concatenate(&notes, SCALE_GAP))
+    }
+    /// Lays chords out back to back, each holding all its tones for
+    /// `chord_duration` seconds. Chord `i` is `kinds[i]` built on
+    /// `roots[i]`; `kinds` repeats when shorter, so one kind can serve
+    /// every root.
+    pub fn progression(&self, roots: &[i64], kinds: &[Chord], chord_duration: f64) -> Sequence {
+        let events = roots
+            .iter()
+            .zip(kinds.iter().cycle())
+            .enumerate()
+            .flat_map(|(index, (&root, kind))| {
+                let start = index as f64 * chord_duration;
+                kind.notes(root)
+                    .into_iter()
+                    .map(move |note| Event::new(start, chord_duration, note))
+            })
+            .collect();
+        Sequence::new(events)
+    }
+}
+
+fn positive(name: &str, value: f64) -> Result<f64> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} must be positive, got {}", name, value),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis, testing, wave_forms};
+
+    #[test]
+    fn render_scale_concatenates_ascending_notes() {
+        let path = testing::temp_path("scale.wav");
+        let ruler = Ruler::default();
+        ruler
+            .render_scale(wave_forms::sin, 0.25, -3, 5, &path)
+            .unwrap();
+        let (samples, spec) = wav::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let note = crate::duration_to_frame(0.25);
+        let gap = crate::duration_to_frame(SCALE_GAP);
+        let channels = spec.channels as usize;
+        assert_eq!(samples.len() / channels, 5 * note + 4 * gap);
+        let mono = mix::to_mono(&samples, channels);
+        let pitches: Vec<f64> = (0..5)
+            .map(|index| {
+                let start = index * (note + gap);
+                analysis::detect_frequency(&mono[start..start + note], 48_000).unwrap()
+            })
+            .collect();
+        for (index, pitch) in pitches.iter().enumerate() {
+            assert!(analysis::cents(*pitch, ruler.frequency(index as i64 - 3)).abs() < 5.0);
+        }
+        assert!(pitches.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn detune_cents_scales_every_frequency() {
+        let ruler = Ruler::default();
+        let semitone = 2.0_f64.powf(1.0 / 12.0);
+        let raised = ruler.clone().with_detune_cents(100.0);
+        let same = ruler.clone().with_detune_cents(0.0);
+        for note in -15..15 {
+            assert!((raised.frequency(note) / ruler.frequency(note) - semitone).abs() < 1e-12);
+            assert_eq!(same.frequency(note), ruler.frequency(note));
+        }
+    }
+
+    fn equal(degrees: usize) -> Ruler {
+        let rations = (0..degrees)
+            .map(|degree| 2.0_f64.powf(degree as f64 / degrees as f64))
+            .collect();
+        Ruler::new(440.0, 120.0, rations).unwrap()
+    }
+
+    #[test]
+    fn octave_boundaries_rise_for_any_table_length() {
+        for ruler in [equal(1), equal(5), equal(7), equal(31), Ruler::default()] {
+            let length = ruler.rations.len() as i64;
+            assert!(ruler.frequency(length - 1) < ruler.frequency(length));
+            assert_eq!(ruler.frequency(length), 2.0 * ruler.frequency);
+            assert_eq!(ruler.frequency(-1), ruler.frequency(length - 1) / 2.0);
+            assert_eq!(ruler.frequency(-length), ruler.frequency / 2.0);
+            let frequencies: Vec<f64> = (-3 * length..3 * length)
+                .map(|note| ruler.frequency(note))
+                .collect();
+            assert!(frequencies.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn new_rejects_unusable_tables() {
+        for (frequency, bpm, rations) in [
+            (440.0, 120.0, vec![]),
+            (440.0, 120.0, vec![1.0, 0.0]),
+            (440.0, 120.0, vec![1.0, f64::NAN]),
+            (0.0, 120.0, vec![1.0]),
+            (440.0, -1.0, vec![1.0]),
+        ] {
+            let error = Ruler::new(frequency, bpm, rations).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+        assert!(Ruler::default().validate().is_ok());
+    }
+
+    #[test]
+    fn progression_stacks_each_chord_at_its_start() {
+        let ruler = Ruler::default();
+        let sequence = ruler.progression(&[0, 5], &[Chord::Major, Chord::Dominant7], 1.5);
+        let at = |start: f64| -> Vec<i64> {
+            sequence
+                .events
+                .iter()
+                .filter(|event| event.start == start)
+                .map(|event| event.note)
+                .collect()
+        };
+        assert_eq!(at(0.0), Chord::Major.notes(0));
+        assert_eq!(at(1.5), Chord::Dominant7.notes(5));
+        assert_eq!(sequence.events.len(), 7);
+        assert!(sequence.events.iter().all(|event| event.duration == 1.5));
+        assert_eq!(sequence.duration(), 3.0);
+    }
+
+    #[test]
+    fn fluent_setters_validate() {
+        let ruler = Ruler::default()
+            .with_bpm(90.0)
+            .unwrap()
+            .with_frequency(432.0)
+            .unwrap();
+        assert_eq!((ruler.bpm, ruler.frequency), (90.0, 432.0));
+        assert_eq!(ruler.rations, Ruler::default().rations);
+        for bpm in [0.0, -60.0, f64::NAN] {
+            let error = Ruler::default().with_bpm(bpm).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+        assert!(Ruler::default().with_frequency(0.0).is_err());
+    }
+
+    #[test]
+    fn equal_tempered_table_has_a_constant_step() {
+        let ruler = equal(12);
+        let table = ruler.frequency_table(-14, 30);
+        assert_eq!(table.len(), 30);
+        assert_eq!(table[0].0, -14);
+        let semitone = 2.0_f64.powf(1.0 / 12.0);
+        for pair in table.windows(2) {
+            assert!((pair[1].1 / pair[0].1 - semitone).abs() < 1e-12);
+        }
+        let text = ruler.format_frequency_table(0, 3);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("440.0000 Hz"));
+        assert!(lines[1].ends_with(&format!("{:.6}", semitone)));
+        assert!(ruler.frequency_table(0, -1).is_empty());
+    }
+
+    #[test]
+    fn random_melody_is_seeded_and_stays_in_the_scale() {
+        let ruler = Ruler::default();
+        let scale = [0, 2, 4, 5, 7, 9, 11];
+        let melody = ruler.random_melody(&scale, 32, 0.25, 11);
+        assert_eq!(
+            melody.events,
+            ruler.random_melody(&scale, 32, 0.25, 11).events
+        );
+        assert_ne!(
+            melody.events,
+            ruler.random_melody(&scale, 32, 0.25, 12).events
+        );
+        assert_eq!(melody.events.len(), 32);
+        for (index, event) in melody.events.iter().enumerate() {
+            assert!(scale.contains(&event.note), "{}", event.note);
+            assert_eq!(event.start, index as f64 * 0.25);
+        }
+        assert!(ruler.random_melody(&[], 8, 0.25, 11).events.is_empty());
+    }
+
+    #[test]
+    fn lerp_runs_between_the_endpoints() {
+        let (a, b) = (Ruler::default(), equal(12));
+        let frequencies =
+            |ruler: &Ruler| -> Vec<f64> { (-12..24).map(|note| ruler.frequency(note)).collect() };
+        for (t, end) in [(0.0, &a), (1.0, &b)] {
+            let blended = a.lerp(&b, t).unwrap();
+            for (x, y) in frequencies(&blended).iter().zip(frequencies(end)) {
+                assert!((x / y - 1.0).abs() < 1e-12);
+            }
+        }
+        let fifth = a.lerp(&b, 0.5).unwrap().rations[7];
+        let (pure, tempered) = (a.rations[7], b.rations[7]);
+        assert!(fifth < pure && fifth > tempered);
+        assert!(
+            (analysis::cents(fifth, tempered) - analysis::cents(pure, tempered) / 2.0).abs() < 1e-9
+        );
+        let error = a.lerp(&equal(7), 0.5).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn intervals_play_together_or_in_turn() {
+        let ruler = Ruler::default();
+        let (root, fifth) = (ruler.frequency(0), ruler.frequency(7));
+        let power = |mono: &[f32], frequency| analysis::goertzel_power(mono, frequency, 48_000);
+        let render = |mode| {
+            let path = testing::temp_path(&format!("interval-{mode:?}.wav"));
+            ruler.render_interval(0, 7, mode, &path).unwrap();
+            let (samples, spec) = wav::read(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            mix::to_mono(&samples, spec.channels as usize)
+        };
+        let note = crate::duration_to_frame(NoteValue::HALF.seconds(ruler.bpm));
+        let harmonic = render(IntervalMode::Harmonic);
+        assert_eq!(harmonic.len(), note);
+        for half in [&harmonic[..note / 2], &harmonic[note / 2..]] {
+            assert!(power(half, root) > 100.0 * power(half, ruler.frequency(3)));
+            assert!((power(half, root) / power(half, fifth) - 1.0).abs() < 0.2);
+        }
+        let melodic = render(IntervalMode::Melodic);
+        assert_eq!(
+            melodic.len(),
+            2 * note + crate::duration_to_frame(SCALE_GAP)
+        );
+        let (first, second) = (&melodic[..note], &melodic[melodic.len() - note..]);
+        assert!(power(first, root) > 100.0 * power(first, fifth));
+        assert!(power(second, fifth) > 100.0 * power(second, root));
+    }
+
+    #[test]
+    fn pattern_places_a_hit_on_every_x() {
+        let sequence = Ruler::default().pattern(4, "x.x.", 0.25, wave_forms::sqr);
+        let starts: Vec<f64> = sequence.events.iter().map(|event| event.start).collect();
+        assert_eq!(starts, [0.0, 0.5]);
+        for event in &sequence.events {
+            assert_eq!((event.note, event.duration), (4, 0.25));
+            assert!(std::ptr::fn_addr_eq(
+                event.waveform.unwrap(),
+                wave_forms::sqr as WaveForm
+            ));
+        }
+        assert!(Ruler::default()
+            .pattern(0, "....", 0.25, wave_forms::sin)
+            .events
+            .is_empty());
+    }
+
+    #[test]
+    fn scala_files_parse_cents_and_ratios() {
+        let path = testing::temp_path("scale.scl");
+        let scale = "! just.scl\n!\nJust major\n 7\n!\n 9/8\n 5/4\n 498.04500\n 3/2\n 5/3\n 15/8 ! leading tone\n 2\n";
+        fs::write(&path, scale).unwrap();
+        let ruler = Ruler::from_scala(&path, 264.0, 90.0).unwrap();
+        let expected = [1.0, 9.0 / 8.0, 1.25, 4.0 / 3.0, 1.5, 5.0 / 3.0, 15.0 / 8.0];
+        assert_eq!(ruler.rations.len(), expected.len());
+        for (ration, expected) in ruler.rations.iter().zip(expected) {
+            assert!((ration - expected).abs() < 1e-6, "{ration} vs {expected}");
+        }
+        assert_eq!((ruler.bpm, ruler.frequency(7)), (90.0, 528.0));
+        fs::write(&path, "bad\n 2\n 3/2\n 3\n").unwrap();
+        let error = Ruler::from_scala(&path, 264.0, 90.0).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn renders_snap_up_to_the_next_bar() {
+        let ruler = Ruler::default().with_bpm(120.0).unwrap();
+        let common = TimeSignature::new(4, 4);
+        assert_eq!(ruler.snap_to_bar(1.7, common), 2.0);
+        assert_eq!(ruler.snap_to_bar(2.0, common), 2.0);
+        assert_eq!(ruler.snap_to_bar(2.1, common), 4.0);
+        let samples = WaveFormerBuilder::new()
+            .duration(1.7)
+            .snap_to_bar(&ruler, common)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        assert_eq!(
+            samples.len(),
+            crate::duration_to_frame(2.0) * crate::constants::CHANNELS
+        );
+    }
+}
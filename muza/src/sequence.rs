@@ -0,0 +1,753 @@
+use std::{io::Result, path::Path};
+
+use rayon::prelude::*;
+
+use crate::{
+    constants, noise::Rng, wav, Instrument, Ruler, WaveForm, WaveFormer, WaveFormerBuilder,
+};
+
+/// One note of a [`Sequence`], timed in seconds from the sequence start.
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub start: f64,
+    pub duration: f64,
+    /// Note index into a [`crate::Ruler`].
+    pub note: i64,
+    /// Dynamics in `[0, 1]`.
+    pub velocity: f64,
+    /// Timbre for this event alone, overriding the sequence's instrument.
+    pub waveform: Option<WaveForm>,
+    /// Stereo position for this event alone, overriding the voice's pan.
+    pub pan: Option<f64>,
+    /// Pitch offset from `note` in cents, for tunings between the ruler's
+    /// notes.
+    pub cents: f64,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        let same_waveform = match (self.waveform, other.waveform) {
+            (Some(a), Some(b)) => std::ptr::fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.start == other.start
+            && self.duration == other.duration
+            && self.note == other.note
+            && self.velocity == other.velocity
+            && self.pan == other.pan
+            && self.cents == other.cents
+            && same_waveform
+    }
+}
+
+impl Event {
+    pub fn new(start: f64, duration: f64, note: i64) -> Self {
+        Self {
+            start,
+            duration,
+            note,
+            velocity: 1.0,
+            waveform: None,
+            pan: None,
+            cents: 0.0,
+        }
+    }
+    pub fn velocity(mut self, velocity: f64) -> Self {
+        self.velocity = velocity;
+        self
+    }
+    pub fn waveform(mut self, waveform: WaveForm) -> Self {
+        self.waveform = Some(waveform);
+        self
+    }
+    pub fn pan(mut self, pan: f64) -> Self {
+        self.pan = Some(pan);
+        self
+    }
+    pub fn cents(mut self, cents: f64) -> Self {
+        self.cents = cents;
+        self
+    }
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+    /// Pitch of the event on `ruler`, including its `cents` offset.
+    pub fn frequency(&self, ruler: &Ruler) -> f64 {
+        ruler.frequency(self.note) * 2.0_f64.powf(self.cents / 1200.0)
+    }
+}
+
+/// Timed notes; gaps between events are rests and events may overlap.
+///
+/// Every event renders through `voice` (envelope, amplitude and the other
+/// render options), with its own waveform if it has one, otherwise one
+/// picked from `instrument` by velocity, and its amplitude scaled by
+/// velocity. The calibration tone, offset, `pad_to`, `snap_to_bar` and
+/// `trim_silence` options shape whole renders, so events ignore them.
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    pub events: Vec<Event>,
+    pub instrument: Instrument,
+    pub voice: WaveFormer,
+    /// Glide time, in seconds, between legato notes; 0 renders every event
+    /// on its own.
+    pub portamento: f64,
+    /// Starts a fresh envelope on a legato note that repeats the previous
+    /// pitch instead of gliding through it. Only matters with a
+    /// `portamento`, since events otherwise always get their own envelope.
+    pub retrigger: bool,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Sequence {
+    pub fn new(events: Vec<Event>) -> Self {
+        Self {
+            events,
+            instrument: Instrument::default(),
+            voice: WaveFormerBuilder::new().build(),
+            portamento: 0.0,
+            retrigger: false,
+        }
+    }
+    fn with_events(&self, events: Vec<Event>) -> Sequence {
+        Sequence {
+            events,
+            instrument: self.instrument.clone(),
+            voice: self.voice.clone(),
+            portamento: self.portamento,
+            retrigger: self.retrigger,
+        }
+    }
+    /// Time of the latest event end, 0 for an empty sequence.
+    pub fn duration(&self) -> f64 {
+        self.events.iter().map(Event::end).fold(0.0, f64::max)
+    }
+    fn sort(&mut self) {
+        self.events.sort_by(|a, b| a.start.total_cmp(&b.start));
+    }
+    /// Plays the sequence backwards: every event is mirrored in time, so
+    /// its end lands where its start was, keeping durations, rests and
+    /// overlaps intact.
+    pub fn retrograde(&self) -> Sequence {
+        let total = self.duration();
+        let mut sequence = self.with_events(
+            self.events
+                .iter()
+                .map(|event| Event {
+                    start: total - event.end(),
+                    ..event.clone()
+                })
+                .collect(),
+        );
+        sequence.sort();
+        sequence
+    }
+    fn map_notes<F: Fn(i64) -> i64>(&self, map: F) -> Sequence {
+        self.with_events(
+            self.events
+                .iter()
+                .map(|event| Event {
+                    note: map(event.note),
+                    ..event.clone()
+                })
+                .collect(),
+        )
+    }
+    /// Shifts every note index by `semitones`.
+    pub fn transpose(&self, semitones: i64) -> Sequence {
+        self.map_notes(|note| note + semitones)
+    }
+    /// Mirrors every note index around `axis`, negating its interval.
+    pub fn invert(&self, axis: i64) -> Sequence {
+        self.map_notes(|note| 2 * axis - note)
+    }
+    /// Moves each start toward the nearest multiple of `grid` seconds;
+    /// `strength` 1.0 snaps fully, 0.5 goes half-way.
+    pub fn quantize(&mut self, grid: f64, strength: f64) {
+        if grid <= 0.0 {
+            return;
+        }
+        for event in &mut self.events {
+            let target = (event.start / grid).round() * grid;
+            event.start += (target - event.start) * strength;
+        }
+        self.sort();
+    }
+    /// Jitters every start by up to `timing_ms` milliseconds and every
+    /// velocity by up to `velocity_amount`, drawn from `seed`. Starts stay
+    /// non-negative and velocities stay in `[0, 1]`.
+    pub fn humanize(&mut self, timing_ms: f64, velocity_amount: f64, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for event in &mut self.events {
+            event.start = (event.start + rng.bipolar() * timing_ms / 1000.0).max(0.0);
+            event.velocity = (event.velocity + rng.bipolar() * velocity_amount).clamp(0.0, 1.0);
+        }
+    }
+    /// Merges each event into the one just before it in time when both
+    /// have the same pitch and the gap between them is less than
+    /// `gap_tolerance` seconds (overlaps count), holding one longer note
+    /// instead. Any other event in between, or different velocities,
+    /// waveforms, pans or cents, keep them separate.
+    pub fn tie(&mut self, gap_tolerance: f64) {
+        self.sort();
+        let mut tied: Vec<Event> = Vec::with_capacity(self.events.len());
+        for event in self.events.drain(..) {
+            if let Some(previous) = tied.last_mut() {
+                let same = Event {
+                    start: previous.start,
+                    duration: previous.duration,
+                    ..event.clone()
+                } == *previous;
+                if same && event.start - previous.end() < gap_tolerance {
+                    previous.duration = previous.end().max(event.end()) - previous.start;
+                    continue;
+                }
+            }
+            tied.push(event);
+        }
+        self.events = tied;
+    }
+    /// A copy with every event `cents` higher, to layer with the original
+    /// for a chorused double.
+    pub fn detune_cents(&self, cents: f64) -> Sequence {
+        self.with_events(
+            self.events
+                .iter()
+                .map(|event| Event {
+                    cents: event.cents + cents,
+                    ..event.clone()
+                })
+                .collect(),
+        )
+    }
+    /// Plays `other` alongside this sequence, keeping absolute times.
+    pub fn layer(&self, other: &Sequence) -> Sequence {
+        let mut sequence =
+            self.with_events(self.events.iter().chain(&other.events).cloned().collect());
+        sequence.sort();
+        sequence
+    }
+    /// Plays `other` once this sequence ends.
+    pub fn then(&self, other: &Sequence) -> Sequence {
+        let offset = self.duration();
+        let mut events = self.events.clone();
+        events.extend(other.events.iter().map(|event| Event {
+            start: event.start + offset,
+            ..event.clone()
+        }));
+        self.with_events(events)
+    }
+    fn event_voice(&self, ruler: &Ruler, event: &Event) -> WaveFormer {
+        WaveFormer {
+            waveform: event
+                .waveform
+                .unwrap_or_else(|| self.instrument.waveform_for(event.velocity)),
+            frequency: event.frequency(ruler),
+            duration: event.duration,
+            amplitude: self.voice.amplitude * event.velocity,
+            pan: event.pan.unwrap_or(self.voice.pan),
+            // Options that shape a whole render would otherwise repeat
+            // on every event and shift or stretch it on the timeline.
+            calibration_tone: false,
+            offset: 0.0,
+            pad_to: None,
+            bar: None,
+            trim_silence: None,
+            ..self.voice.clone()
+        }
+    }
+    /// Frames the rendered timeline spans, at the voice's sample rate.
+    pub fn total_frames(&self) -> usize {
+        self.events
+            .iter()
+            .map(|event| {
+                self.voice.duration_to_frame(event.start)
+                    + self.voice.duration_to_frame(event.duration)
+            })
+            .fold(self.voice.duration_to_frame(self.duration()), usize::max)
+    }
+    /// Size of the file [`Sequence::render`] writes, cue chunk included.
+    pub fn estimated_bytes(&self) -> usize {
+        self.voice.bit_depth.file_size(self.total_frames()) + wav::cue_chunk_size(self.events.len())
+    }
+    /// Groups time-ordered events into runs where each note starts before
+    /// or exactly when the previous one ends, breaking on repeated pitches
+    /// when `retrigger` is set.
+    fn legato_phrases(&self) -> Vec<Vec<&Event>> {
+        let mut events: Vec<_> = self.events.iter().collect();
+        events.sort_by(|a, b| a.start.total_cmp(&b.start));
+        let mut phrases: Vec<Vec<&Event>> = Vec::new();
+        for event in events {
+            let legato = |previous: &Event| {
+                event.start <= previous.end() && !(self.retrigger && event.note == previous.note)
+            };
+            match phrases.last_mut() {
+                Some(phrase) if legato(phrase[phrase.len() - 1]) => phrase.push(event),
+                _ => phrases.push(vec![event]),
+            }
+        }
+        phrases
+    }
+    /// Renders a legato run as one phase-continuous voice with the first
+    /// note's waveform, pan and one envelope. Each later note glides from the
+    /// pitch sounding at its start to its own over `portamento` seconds,
+    /// exponentially so the glide is even in pitch, and sets the level to
+    /// its velocity.
+    fn render_phrase(&self, ruler: &Ruler, phrase: &[&Event]) -> Result<Vec<f32>> {
+        let first = phrase[0];
+        let end = phrase.iter().map(|event| event.end()).fold(0.0, f64::max);
+        let voice = WaveFormer {
+            duration: end - first.start,
+            amplitude: self.voice.amplitude,
+            ..self.event_voice(ruler, first)
+        };
+        let targets: Vec<_> = phrase.iter().map(|event| event.frequency(ruler)).collect();
+        let glide = |from: f64, to: f64, elapsed: f64| {
+            let progress = if self.portamento > 0.0 {
+                (elapsed / self.portamento).clamp(0.0, 1.0)
+            } else {
+                1.0
+            };
+            from * (to / from).powf(progress)
+        };
+        let mut origins = vec![targets[0]];
+        for index in 1..phrase.len() {
+            let elapsed = phrase[index].start - phrase[index - 1].start;
+            origins.push(glide(origins[index - 1], targets[index - 1], elapsed));
+        }
+        let current = |seconds: f64| {
+            phrase
+                .partition_point(|event| event.start - first.start <= seconds)
+                .saturating_sub(1)
+        };
+        voice.render_to_buffer_with_pitch(
+            |seconds| phrase[current(seconds)].velocity,
+            |seconds| {
+                let index = current(seconds);
+                let elapsed = seconds - (phrase[index].start - first.start);
+                glide(origins[index], targets[index], elapsed) / targets[0]
+            },
+        )
+    }
+    /// Units rendered as one voice each: legato runs with a `portamento`,
+    /// otherwise single events in their original order.
+    fn parts(&self) -> Vec<Vec<&Event>> {
+        if self.portamento > 0.0 {
+            self.legato_phrases()
+        } else {
+            self.events.iter().map(|event| vec![event]).collect()
+        }
+    }
+    fn render_part(&self, ruler: &Ruler, part: &[&Event]) -> Result<Vec<f32>> {
+        if self.portamento > 0.0 {
+            self.render_phrase(ruler, part)
+        } else {
+            self.event_voice(ruler, part[0]).render_to_buffer()
+        }
+    }
+    /// Sums rendered parts onto one timeline, in order.
+    fn mix_parts(&self, parts: &[Vec<&Event>], buffers: Vec<Vec<f32>>) -> Vec<f32> {
+        let channels = constants::CHANNELS;
+        let mut bus = vec![0.0; self.total_frames() * channels];
+        for (part, samples) in parts.iter().zip(buffers) {
+            let offset = self.voice.duration_to_frame(part[0].start) * channels;
+            for (out, sample) in bus[offset..].iter_mut().zip(&samples) {
+                *out += sample;
+            }
+        }
+        bus
+    }
+    /// Renders every event and sums them on one interleaved timeline. With
+    /// a `portamento`, legato runs render as single gliding voices instead.
+    pub fn render_to_buffer(&self, ruler: &Ruler) -> Result<Vec<f32>> {
+        let parts = self.parts();
+        let buffers = parts
+            .iter()
+            .map(|part| self.render_part(ruler, part))
+            .collect::<Result<_>>()?;
+        Ok(self.mix_parts(&parts, buffers))
+    }
+    /// Like [`Sequence::render_to_buffer`], synthesizing the events on
+    /// rayon's thread pool. Only the synthesis runs in parallel; the buffers
+    /// are summed afterwards on the calling thread in the serial order, so
+    /// the result is bit-identical to the serial one.
+    pub fn render_parallel(&self, ruler: &Ruler) -> Result<Vec<f32>> {
+        let parts = self.parts();
+        let buffers = parts
+            .par_iter()
+            .map(|part| self.render_part(ruler, part))
+            .collect::<Result<_>>()?;
+        Ok(self.mix_parts(&parts, buffers))
+    }
+    /// Frame offsets of every event start, in time order.
+    pub fn markers(&self) -> Vec<usize> {
+        let mut markers: Vec<_> = self
+            .events
+            .iter()
+            .map(|event| self.voice.duration_to_frame(event.start))
+            .collect();
+        markers.sort_unstable();
+        markers
+    }
+    /// Renders to a WAV file with a cue point at every event start, so
+    /// editors can jump between notes.
+    pub fn render<S: AsRef<Path>>(&self, ruler: &Ruler, path: S) -> Result<()> {
+        wav::write_with_spec(&path, &self.render_to_buffer(ruler)?, self.voice.spec())?;
+        wav::write_cue_points(path, &self.markers())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::Adsr;
+    use crate::{analysis, testing, wave_forms};
+
+    fn line(notes: &[i64]) -> Sequence {
+        Sequence::new(
+            notes
+                .iter()
+                .enumerate()
+                .map(|(index, &note)| Event::new(index as f64 * 0.5, 0.5, note))
+                .collect(),
+        )
+    }
+
+    fn notes(sequence: &Sequence) -> Vec<i64> {
+        sequence.events.iter().map(|event| event.note).collect()
+    }
+
+    #[test]
+    fn retrograde_reverses_an_ascending_line() {
+        let sequence = line(&[0, 2, 4]);
+        let reversed = sequence.retrograde();
+        assert_eq!(notes(&reversed), [4, 2, 0]);
+        assert_eq!(reversed.duration(), sequence.duration());
+        let starts: Vec<f64> = reversed.events.iter().map(|event| event.start).collect();
+        assert_eq!(starts, [0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn retrograde_keeps_rests_and_overlaps() {
+        let sequence = Sequence::new(vec![Event::new(0.0, 1.0, 0), Event::new(0.5, 0.25, 1)]);
+        let reversed = sequence.retrograde();
+        assert_eq!(reversed.events[0].start, 0.0);
+        assert_eq!(reversed.events[0].note, 0);
+        assert_eq!(reversed.events[1].start, 0.25);
+        assert_eq!(reversed.duration(), 1.0);
+    }
+
+    #[test]
+    fn transpose_shifts_frequencies_by_the_interval_ratio() {
+        let equal = Ruler {
+            rations: (0..12)
+                .map(|degree| 2.0_f64.powf(degree as f64 / 12.0))
+                .collect(),
+            ..Ruler::default()
+        };
+        let sequence = line(&[-1, 0, 5]);
+        for semitones in [1, 7, 12, -5] {
+            let transposed = sequence.transpose(semitones);
+            let ratio = 2.0_f64.powf(semitones as f64 / 12.0);
+            for (shifted, event) in transposed.events.iter().zip(&sequence.events) {
+                let expected = event.frequency(&equal) * ratio;
+                assert!((shifted.frequency(&equal) / expected - 1.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn invert_negates_intervals_around_the_axis() {
+        let sequence = line(&[2, 5, 1]);
+        let inverted = sequence.invert(2);
+        assert_eq!(notes(&inverted), [2, -1, 3]);
+        let intervals = |notes: Vec<i64>| -> Vec<i64> {
+            notes.windows(2).map(|pair| pair[1] - pair[0]).collect()
+        };
+        let negated: Vec<i64> = intervals(notes(&sequence))
+            .iter()
+            .map(|step| -step)
+            .collect();
+        assert_eq!(intervals(notes(&inverted)), negated);
+    }
+
+    #[test]
+    fn quantize_snaps_by_strength() {
+        let quantized = |strength| {
+            let mut sequence = Sequence::new(vec![Event::new(0.51, 0.25, 0)]);
+            sequence.quantize(0.25, strength);
+            sequence.events[0].start
+        };
+        assert!((quantized(1.0) - 0.5).abs() < 1e-12);
+        assert!((quantized(0.5) - 0.505).abs() < 1e-12);
+        assert!((quantized(0.0) - 0.51).abs() < 1e-12);
+    }
+
+    #[test]
+    fn humanize_is_seeded_and_bounded() {
+        let original = Sequence::new(
+            (0..32)
+                .map(|index| Event::new(0.1 + index as f64 * 0.25, 0.25, 0).velocity(0.5))
+                .collect(),
+        );
+        let humanized = |seed| {
+            let mut sequence = original.clone();
+            sequence.humanize(20.0, 0.1, seed);
+            sequence
+        };
+        let jittered = humanized(3);
+        assert_eq!(jittered.events, humanized(3).events);
+        assert_ne!(jittered.events, humanized(4).events);
+        for (event, before) in jittered.events.iter().zip(&original.events) {
+            assert!((event.start - before.start).abs() <= 0.020);
+            assert!((event.velocity - before.velocity).abs() <= 0.1);
+            assert_eq!(event.duration, before.duration);
+        }
+    }
+
+    #[test]
+    fn render_writes_a_cue_point_per_event() {
+        let path = testing::temp_path("cues.wav");
+        let sequence = Sequence::new(vec![
+            Event::new(0.0, 0.2, 0),
+            Event::new(0.25, 0.2, 2),
+            Event::new(0.6, 0.2, 4),
+        ]);
+        sequence.render(&Ruler::default(), &path).unwrap();
+        let cues = wav::read_cue_points(&path).unwrap();
+        let (samples, _) = wav::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cues, [0, 12_000, 28_800]);
+        assert_eq!(samples.len(), sequence.total_frames() * constants::CHANNELS);
+    }
+
+    #[test]
+    fn total_frames_matches_the_rendered_length() {
+        for sequence in [
+            line(&[0, 2, 4]),
+            Sequence::new(vec![Event::new(0.0, 1.0, 0), Event::new(0.3, 0.2, 5)]),
+            Sequence::new(vec![Event::new(0.125, 0.1, 0)]),
+        ] {
+            let samples = sequence.render_to_buffer(&Ruler::default()).unwrap();
+            assert_eq!(samples.len(), sequence.total_frames() * constants::CHANNELS);
+        }
+    }
+
+    #[test]
+    fn layer_keeps_every_event_and_then_follows_on() {
+        let first = line(&[0, 2, 4]);
+        let second = line(&[7, 5]);
+        let layered = first.layer(&second);
+        assert_eq!(layered.events.len(), 5);
+        assert_eq!(layered.duration(), first.duration());
+        let joined = first.then(&second);
+        assert_eq!(joined.events.len(), 5);
+        assert_eq!(joined.events[3].start, first.duration());
+        assert_eq!(joined.events[3].note, 7);
+        assert_eq!(joined.duration(), first.duration() + second.duration());
+    }
+
+    #[test]
+    fn each_event_renders_with_its_own_waveform() {
+        let ruler = Ruler::default();
+        let sequence = Sequence::new(vec![
+            Event::new(0.0, 0.5, 0).waveform(wave_forms::sin),
+            Event::new(0.5, 0.5, 0).waveform(wave_forms::sqr),
+        ]);
+        let mono = crate::mix::to_mono(&sequence.render_to_buffer(&ruler).unwrap(), 2);
+        let fundamental = ruler.frequency(0);
+        // Third harmonic over fundamental, in a window clear of both ends.
+        let third = |start: usize| {
+            let window = &mono[start..start + 12_000];
+            analysis::goertzel_power(window, 3.0 * fundamental, 48_000)
+                / analysis::goertzel_power(window, fundamental, 48_000)
+        };
+        assert!(third(6_000) < 1e-4);
+        assert!(third(30_000) > 0.05);
+    }
+
+    /// Detected pitch of the mono mix over `start..start + 0.02` seconds.
+    fn pitch_at(mono: &[f32], start: f64) -> f64 {
+        let frame = crate::duration_to_frame(start);
+        analysis::detect_frequency(&mono[frame..frame + 960], 48_000).unwrap()
+    }
+
+    #[test]
+    fn portamento_glides_between_legato_notes() {
+        let ruler = Ruler::default();
+        let mut sequence = Sequence::new(vec![Event::new(0.0, 0.5, 0), Event::new(0.5, 0.5, 12)]);
+        sequence.portamento = 0.2;
+        let mono = crate::mix::to_mono(&sequence.render_to_buffer(&ruler).unwrap(), 2);
+        let pitches: Vec<f64> = [0.4, 0.53, 0.59, 0.65, 0.8]
+            .into_iter()
+            .map(|start| pitch_at(&mono, start))
+            .collect();
+        assert!(
+            analysis::cents(pitches[0], 440.0).abs() < 10.0,
+            "{pitches:?}"
+        );
+        assert!(
+            analysis::cents(pitches[4], 880.0).abs() < 10.0,
+            "{pitches:?}"
+        );
+        assert!(
+            pitches.windows(2).all(|pair| pair[0] < pair[1]),
+            "{pitches:?}"
+        );
+        // The glide is even in pitch: the window centred half-way through
+        // sits half an octave up.
+        assert!(analysis::cents(pitches[2], 440.0 * 2.0_f64.sqrt()).abs() < 60.0);
+    }
+
+    /// Peak level of the mono mix over `start..start + 0.01` seconds.
+    fn level_at(mono: &[f32], start: f64) -> f32 {
+        let frame = crate::duration_to_frame(start);
+        mono[frame..frame + 480]
+            .iter()
+            .fold(0.0, |peak, sample| peak.max(sample.abs()))
+    }
+
+    #[test]
+    fn retrigger_gives_repeated_notes_their_own_attack() {
+        let render = |retrigger| {
+            let mut sequence =
+                Sequence::new(vec![Event::new(0.0, 0.5, 3), Event::new(0.5, 0.5, 3)]);
+            sequence.voice = WaveFormerBuilder::new()
+                .envelope(Adsr::new(0.1, 0.0, 1.0, 0.0))
+                .build();
+            sequence.portamento = 0.05;
+            sequence.retrigger = retrigger;
+            crate::mix::to_mono(&sequence.render_to_buffer(&Ruler::default()).unwrap(), 2)
+        };
+        let (held, retriggered) = (render(false), render(true));
+        let sustain = level_at(&held, 0.3);
+        assert!(level_at(&held, 0.5) > 0.9 * sustain);
+        assert!(level_at(&retriggered, 0.5) < 0.2 * sustain);
+        assert!(level_at(&retriggered, 0.8) > 0.9 * sustain);
+    }
+
+    #[test]
+    fn event_pan_places_each_note() {
+        let sequence = Sequence::new(vec![
+            Event::new(0.0, 0.5, 0).pan(-1.0),
+            Event::new(0.5, 0.5, 0).pan(1.0),
+        ]);
+        let samples = sequence.render_to_buffer(&Ruler::default()).unwrap();
+        let energy = |range: std::ops::Range<usize>, channel: usize| -> f64 {
+            samples[range.start * 2..range.end * 2]
+                .iter()
+                .skip(channel)
+                .step_by(2)
+                .map(|&sample| (sample as f64).powi(2))
+                .sum()
+        };
+        let (first, second) = (0..24_000, 24_000..48_000);
+        assert!(energy(first.clone(), 0) > 100.0 * energy(first, 1));
+        assert!(energy(second.clone(), 1) > 100.0 * energy(second, 0));
+    }
+
+    #[test]
+    fn events_ignore_whole_render_options() {
+        let ruler = Ruler::default();
+        let plain = line(&[0, 4, 7]).render_to_buffer(&ruler).unwrap();
+        let mut sequence = line(&[0, 4, 7]);
+        sequence.voice = WaveFormerBuilder::new()
+            .calibration_tone(true)
+            .offset(0.25)
+            .pad_to(2.0)
+            .snap_to_bar(&ruler, crate::TimeSignature::new(4, 4))
+            .trim_silence(0.5)
+            .build();
+        assert!(sequence.render_to_buffer(&ruler).unwrap() == plain);
+        sequence.portamento = 0.05;
+        let mut gliding = line(&[0, 4, 7]);
+        gliding.portamento = 0.05;
+        assert!(
+            sequence.render_to_buffer(&ruler).unwrap() == gliding.render_to_buffer(&ruler).unwrap()
+        );
+    }
+
+    #[test]
+    fn parallel_render_is_bit_identical_to_serial() {
+        let ruler = Ruler::default();
+        let mut busy = Sequence::new(
+            (0..64)
+                .map(|index| {
+                    Event::new(index as f64 * 0.03, 0.2, index % 12)
+                        .velocity(0.3 + (index % 5) as f64 / 10.0)
+                })
+                .collect(),
+        );
+        let serial = busy.render_to_buffer(&ruler).unwrap();
+        assert!(busy.render_parallel(&ruler).unwrap() == serial);
+        busy.portamento = 0.02;
+        let serial = busy.render_to_buffer(&ruler).unwrap();
+        assert!(busy.render_parallel(&ruler).unwrap() == serial);
+        assert!(Sequence::default()
+            .render_parallel(&ruler)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn tie_merges_only_the_immediately_preceding_same_note() {
+        let mut sequence = Sequence::new(vec![
+            Event::new(0.0, 0.5, 4),
+            Event::new(0.5, 0.25, 4),
+            Event::new(1.0, 0.5, 7),
+        ]);
+        sequence.tie(1e-3);
+        assert_eq!(notes(&sequence), [4, 7]);
+        assert_eq!(sequence.events[0].duration, 0.75);
+        let mut interrupted = Sequence::new(vec![
+            Event::new(0.0, 0.5, 4),
+            Event::new(0.5, 0.5, 7),
+            Event::new(1.0, 0.5, 4),
+        ]);
+        interrupted.tie(1.0);
+        assert_eq!(notes(&interrupted), [4, 7, 4]);
+        let mut gapped = Sequence::new(vec![Event::new(0.0, 0.5, 4), Event::new(0.75, 0.5, 4)]);
+        gapped.tie(0.25);
+        assert_eq!(
+            gapped.events.len(),
+            2,
+            "gaps must be strictly below the tolerance"
+        );
+        let mut accented = Sequence::new(vec![
+            Event::new(0.0, 0.5, 4),
+            Event::new(0.5, 0.5, 4).velocity(0.5),
+        ]);
+        accented.tie(1.0);
+        assert_eq!(accented.events.len(), 2);
+    }
+
+    #[test]
+    fn detuned_double_beats_against_the_original() {
+        let ruler = Ruler::default();
+        let lead = Sequence::new(vec![Event::new(0.0, 1.0, 0)]);
+        let swing = |layered: &Sequence| {
+            let mono = crate::mix::to_mono(
+                &layered.render_to_buffer(&ruler).unwrap(),
+                constants::CHANNELS,
+            );
+            let levels: Vec<f64> = mono
+                .chunks_exact(480)
+                .map(|window| {
+                    (window.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / 480.0).sqrt()
+                })
+                .collect();
+            let max = levels.iter().copied().fold(0.0, f64::max);
+            let min = levels.iter().copied().fold(f64::INFINITY, f64::min);
+            max / min
+        };
+        let detuned = lead.detune_cents(10.0);
+        assert_eq!(detuned.events[0].cents, 10.0);
+        assert!(swing(&lead.layer(&detuned)) > 5.0);
+        assert!(swing(&lead.layer(&lead)) < 1.1);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use crate::{constants, mix, pan, wav, Ruler, Sequence};
+
+/// One part of a [`Song`]: a sequence with its mixer settings.
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub sequence: Sequence,
+    pub gain: f64,
+    /// Stereo balance in `[-1, 1]`, left to right, on top of any panning
+    /// inside the sequence.
+    pub pan: f64,
+}
+
+impl Track {
+    pub fn new(sequence: Sequence) -> Self {
+        Self {
+            sequence,
+            gain: 1.0,
+            pan: 0.0,
+        }
+    }
+    pub fn gain(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+    pub fn pan(mut self, pan: f64) -> Self {
+        self.pan = pan;
+        self
+    }
+    /// The sequence rendered with the track's gain and pan applied.
+    pub fn render_to_buffer(&self, ruler: &Ruler) -> Result<Vec<f32>> {
+        let mut samples = self.sequence.render_to_buffer(ruler)?;
+        let channels = constants::CHANNELS;
+        let gains: Vec<f32> = (0..channels)
+            .map(|channel| (self.gain * pan::channel_gain(self.pan, channel, channels)) as f32)
+            .collect();
+        for frame in samples.chunks_exact_mut(channels) {
+            for (sample, gain) in frame.iter_mut().zip(&gains) {
+                *sample *= gain;
+            }
+        }
+        Ok(samples)
+    }
+}
+
+/// Tracks sharing one timeline, all starting at time 0.
+#[derive(Clone, Debug, Default)]
+pub struct Song {
+    pub tracks: Vec<Track>,
+}
+
+impl Song {
+    pub fn new(tracks: Vec<Track>) -> Self {
+        Self { tracks }
+    }
+    /// Mixes every track; the result is as long as the longest track.
+    /// Fails like [`Song::spec`] when the tracks' sample rates differ.
+    pub fn render_to_buffer(&self, ruler: &Ruler) -> Result<Vec<f32>> {
+        self.spec()?;
+        let mut buffers = Vec::with_capacity(self.tracks.len());
+        for track in &self.tracks {
+            buffers.push(track.render_to_buffer(ruler)?);
+        }
+        Ok(mix::sum(&buffers))
+    }
+    /// Size of the file [`Song::render`] writes, header included, in the
+    /// first track's bit depth like [`Song::spec`].
+    pub fn estimated_bytes(&self) -> usize {
+        let frames = self
+            .tracks
+            .iter()
+            .map(|track| track.sequence.total_frames())
+            .max()
+            .unwrap_or(0);
+        self.tracks
+            .first()
+            .map_or(wav::BitDepth::default(), |track| {
+                track.sequence.voice.bit_depth
+            })
+            .file_size(frames)
+    }
+    /// Format of the file [`Song::render`] writes: the tracks' shared
+    /// sample rate and the first track's bit depth, or the defaults for a
+    /// song without tracks. Fails with [`ErrorKind::InvalidInput`] when the
+    /// tracks' sample rates differ, since their frames wouldn't line up.
+    pub fn spec(&self) -> Result<hound::WavSpec> {
+        let Some(first) = self.tracks.first() else {
+            return Ok(wav::spec());
+        };
+        let sample_rate = first.sequence.voice.sample_rate;
+        if let Some(track) = self
+            .tracks
+            .iter()
+            .find(|track| track.sequence.voice.sample_rate != sample_rate)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "tracks mix {} Hz with {} Hz",
+                    sample_rate, track.sequence.voice.sample_rate
+                ),
+            ));
+        }
+        Ok(first.sequence.voice.bit_depth.spec(sample_rate))
+    }
+    pub fn render<S: AsRef<Path>>(&self, ruler: &Ruler, path: S) -> Result<()> {
+        let spec = self.spec()?;
+        wav::write_with_spec(path, &self.render_to_buffer(ruler)?, spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis, Event, WaveFormerBuilder};
+
+    fn track(note: i64, duration: f64) -> Track {
+        Track::new(Sequence::new(vec![Event::new(0.0, duration, note)]))
+    }
+
+    #[test]
+    fn song_mixes_tracks_with_their_gains_over_the_longest() {
+        let ruler = Ruler::default();
+        let (low, high) = (track(0, 0.5), track(7, 0.25));
+        let song = Song::new(vec![low.clone().gain(0.5), high.clone().gain(0.25)]);
+        let mixed = song.render_to_buffer(&ruler).unwrap();
+        let low = low.render_to_buffer(&ruler).unwrap();
+        let high = high.render_to_buffer(&ruler).unwrap();
+        assert_eq!(mixed.len(), low.len().max(high.len()));
+        for (index, &sample) in mixed.iter().enumerate() {
+            let expected = 0.5 * low[index] + 0.25 * high.get(index).copied().unwrap_or(0.0);
+            assert!((sample - expected).abs() < 1e-6);
+        }
+        let mono = mix::to_mono(&mixed[..high.len()], constants::CHANNELS);
+        let power = |note| analysis::goertzel_power(&mono, ruler.frequency(note), 48_000);
+        assert!(
+            (power(0) / power(7) - 4.0).abs() < 0.4,
+            "{}",
+            power(0) / power(7)
+        );
+    }
+
+    #[test]
+    fn render_uses_the_tracks_format_and_rejects_mixed_rates() {
+        let mut low = track(0, 0.1);
+        low.sequence.voice = WaveFormerBuilder::new()
+            .sample_rate(44_100)
+            .bit_depth(wav::BitDepth::Int16)
+            .build();
+        let path = crate::testing::temp_path("song.wav");
+        let song = Song::new(vec![low.clone(), low.clone()]);
+        song.render(&Ruler::default(), &path).unwrap();
+        let spec = hound::WavReader::open(&path).unwrap().spec();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((spec.sample_rate, spec.bits_per_sample), (44_100, 16));
+        let song = Song::new(vec![low, track(0, 0.1)]);
+        let error = song.render_to_buffer(&Ruler::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn estimated_bytes_match_what_sequences_and_songs_write() {
+        let ruler = Ruler::default();
+        let path = crate::testing::temp_path("song-estimate.wav");
+        let mut sequence = Sequence::new(vec![Event::new(0.0, 0.25, 0), Event::new(0.5, 0.25, 4)]);
+        sequence.voice = WaveFormerBuilder::new()
+            .bit_depth(wav::BitDepth::Int16)
+            .build();
+        sequence.render(&ruler, &path).unwrap();
+        let size = |path: &std::path::PathBuf| std::fs::metadata(path).unwrap().len() as usize;
+        assert_eq!(sequence.estimated_bytes(), size(&path));
+        let song = Song::new(vec![Track::new(sequence), track(7, 1.0)]);
+        song.render(&ruler, &path).unwrap();
+        assert_eq!(song.estimated_bytes(), size(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
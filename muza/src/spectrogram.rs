@@ -0,0 +1,87 @@
+//! Spectrogram images, available with the `spectrogram` feature.
+
+use std::{fs::File, io::BufWriter, io::Result, path::Path};
+
+use crate::{analysis, constants, mix, WaveFormer};
+
+/// Samples per STFT column; the image is `FFT_SIZE / 2` pixels tall.
+pub const FFT_SIZE: usize = 1024;
+/// Samples between consecutive columns.
+pub const HOP: usize = FFT_SIZE / 2;
+/// Magnitudes this far below the loudest bin render black.
+pub const FLOOR_DB: f64 = -90.0;
+
+/// Writes a grayscale PNG of `spectra`, time left to right and frequency
+/// bottom to top, brightness scaled in decibels.
+pub fn write_png<S: AsRef<Path>>(spectra: &[Vec<f64>], path: S) -> Result<()> {
+    let width = spectra.len();
+    let height = spectra.first().map_or(0, Vec::len);
+    let peak = spectra
+        .iter()
+        .flatten()
+        .fold(f64::MIN_POSITIVE, |peak, &magnitude| peak.max(magnitude));
+    let mut pixels = vec![0; width * height];
+    for (x, spectrum) in spectra.iter().enumerate() {
+        for (bin, &magnitude) in spectrum.iter().enumerate() {
+            let db = 20.0 * (magnitude.max(f64::MIN_POSITIVE) / peak).log10();
+            let level = (1.0 - db / FLOOR_DB).clamp(0.0, 1.0);
+            pixels[(height - 1 - bin) * width + x] = (level * 255.0).round() as u8;
+        }
+    }
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    Ok(writer.finish()?)
+}
+
+impl WaveFormer {
+    /// Renders the mono mix and writes its spectrogram as a PNG that is
+    /// one column per [`HOP`] samples wide and `FFT_SIZE / 2` pixels tall.
+    pub fn render_spectrogram<S: AsRef<Path>>(&self, path: S) -> Result<()> {
+        let mono = mix::to_mono(&self.render_to_buffer()?, constants::CHANNELS);
+        write_png(&analysis::stft(&mono, FFT_SIZE, HOP), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing, WaveFormerBuilder};
+    use std::io::BufReader;
+
+    #[test]
+    fn png_has_one_column_per_hop_and_half_the_fft_tall() {
+        let path = testing::temp_path("spectrogram.png");
+        WaveFormerBuilder::new()
+            .frequency(440.0)
+            .duration(1.0)
+            .build()
+            .render_spectrogram(&path)
+            .unwrap();
+        let decoder = png::Decoder::new(BufReader::new(File::open(&path).unwrap()));
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (width, height) = (info.width as usize, info.height as usize);
+        assert_eq!(width, 1 + (48_000 - FFT_SIZE) / HOP);
+        assert_eq!(height, FFT_SIZE / 2);
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+        // The brightest row is the tone's bin, counted up from the bottom.
+        let row_energy = |row: usize| -> u64 {
+            pixels[row * width..(row + 1) * width]
+                .iter()
+                .map(|&pixel| pixel as u64)
+                .sum()
+        };
+        let brightest = (0..height).max_by_key(|&row| row_energy(row)).unwrap();
+        let bin = (440.0 * FFT_SIZE as f64 / 48_000.0).round() as usize;
+        assert_eq!(height - 1 - brightest, bin);
+    }
+}
@@ -0,0 +1,251 @@
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{constants, synth::Synth, wave_former::check_finite, WaveFormer, RENDER_BLOCK};
+
+fn unity(_: f64) -> f64 {
+    1.0
+}
+
+/// Pull-based block renderer behind [`WaveFormer::render_streaming`], for
+/// feeding audio elsewhere or inspecting a streamed render.
+///
+/// Blocks are about [`RENDER_BLOCK`] frames. One oscillator block is held
+/// back so zero alignment can still trim the tail, and the calibration
+/// tone and `offset` silence come first, so the blocks joined together
+/// equal [`WaveFormer::render_to_buffer`] whenever a period fits in a
+/// block. `pad_to` and `snap_to_bar` cut the stream short or follow it
+/// with silence blocks. `trim_silence` and `oversample` need the whole
+/// buffer, so [`StreamingRenderer::new`] rejects them. Under `strict`,
+/// every block is checked as it is produced.
+pub struct StreamingRenderer<'a> {
+    waveformer: &'a WaveFormer,
+    synth: Synth<'a, fn(f64) -> f64>,
+    /// Calibration tone and leading silence, emitted as the first block.
+    lead: Vec<f32>,
+    pending: Vec<f32>,
+    done: bool,
+    /// Frames of offset silence and oscillator output handed out so far.
+    emitted: usize,
+    /// Frames the stream stops at once the oscillator is done, known then.
+    end: Option<usize>,
+    /// Block [`StreamingRenderer::fill_ring`] is copying from, and how
+    /// much of it has been copied.
+    ring: Vec<f32>,
+    ring_read: usize,
+}
+
+impl<'a> StreamingRenderer<'a> {
+    /// Fails with [`ErrorKind::InvalidInput`] when `trim_silence` or
+    /// `oversample` is set.
+    pub fn new(waveformer: &'a WaveFormer) -> Result<Self> {
+        if waveformer.trim_silence.is_some() || waveformer.oversample > 1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "trim_silence and oversample need the whole buffer and can't stream",
+            ));
+        }
+        let synth = Synth::new(waveformer, unity as fn(f64) -> f64)?;
+        let mut lead = Vec::new();
+        if waveformer.calibration_tone {
+            lead = waveformer.render_calibration()?;
+        }
+        let mut offset = waveformer.duration_to_frame(waveformer.offset);
+        if let Some(duration) = waveformer.pad_to {
+            offset = offset.min(waveformer.duration_to_frame(duration));
+        }
+        lead.resize(lead.len() + offset * constants::CHANNELS, 0.0);
+        Ok(Self {
+            waveformer,
+            synth,
+            lead,
+            pending: Vec::new(),
+            done: false,
+            emitted: offset,
+            end: None,
+            ring: Vec::new(),
+            ring_read: 0,
+        })
+    }
+    /// Replaces `buf` with the next block of interleaved samples; false,
+    /// with `buf` empty, once the render is complete. Under `strict`, fails
+    /// with [`ErrorKind::InvalidData`] and the frame index, counted from
+    /// the start of the render, of the first non-finite sample.
+    pub fn next_block(&mut self, buf: &mut Vec<f32>) -> Result<bool> {
+        buf.clear();
+        if !self.lead.is_empty() {
+            std::mem::swap(buf, &mut self.lead);
+            return Ok(true);
+        }
+        self.oscillator_block(buf);
+        if let (Some(duration), None) = (self.waveformer.pad_to, self.end) {
+            let left = self.waveformer.duration_to_frame(duration) - self.emitted;
+            if buf.len() / constants::CHANNELS >= left {
+                buf.truncate(left * constants::CHANNELS);
+                self.done = true;
+            }
+        }
+        if buf.is_empty() && self.done {
+            let end = *self
+                .end
+                .get_or_insert_with(|| self.waveformer.padded_frames(self.emitted));
+            let frames = (end - self.emitted).min(RENDER_BLOCK);
+            buf.resize(frames * constants::CHANNELS, 0.0);
+        }
+        if self.waveformer.strict {
+            check_finite(buf, self.waveformer.calibration_frames() + self.emitted)?;
+        }
+        self.emitted += buf.len() / constants::CHANNELS;
+        Ok(!buf.is_empty())
+    }
+    /// Fills `buf` with the next oscillator block, leaving it empty once
+    /// the oscillator is done.
+    fn oscillator_block(&mut self, buf: &mut Vec<f32>) {
+        while !self.done {
+            let mut block = Vec::with_capacity(RENDER_BLOCK * constants::CHANNELS);
+            let frames = self.synth.fill(&mut block, RENDER_BLOCK);
+            if frames > 0 && frames < RENDER_BLOCK {
+                // A short block is the last one: keep it with the held-back
+                // block so the tail search still spans a whole period.
+                self.pending.extend_from_slice(&block);
+            } else if frames > 0 {
+                let previous = std::mem::replace(&mut self.pending, block);
+                if !previous.is_empty() {
+                    *buf = previous;
+                    return;
+                }
+            } else {
+                self.done = true;
+                if self.waveformer.zero_align {
+                    self.waveformer.align_tail(&mut self.pending);
+                }
+                std::mem::swap(buf, &mut self.pending);
+            }
+        }
+    }
+    /// Overwrites all of `out` with the next interleaved samples, for audio
+    /// callbacks whose buffer size doesn't match the blocks; any length
+    /// works, and consecutive calls continue where the last one stopped.
+    /// Silence follows once the render is complete. Samples already taken
+    /// with [`StreamingRenderer::next_block`] aren't repeated, but a block
+    /// this has started is only finished by further calls to it. Fails
+    /// like [`StreamingRenderer::next_block`].
+    pub fn fill_ring(&mut self, out: &mut [f32]) -> Result<()> {
+        let mut written = 0;
+        while written < out.len() {
+            if self.ring_read == self.ring.len() {
+                let mut block = std::mem::take(&mut self.ring);
+                self.ring_read = 0;
+                if !self.next_block(&mut block)? {
+                    out[written..].fill(0.0);
+                    return Ok(());
+                }
+                self.ring = block;
+            }
+            let count = (out.len() - written).min(self.ring.len() - self.ring_read);
+            out[written..written + count]
+                .copy_from_slice(&self.ring[self.ring_read..self.ring_read + count]);
+            written += count;
+            self.ring_read += count;
+        }
+        Ok(())
+    }
+    /// Pulls every remaining block into one buffer.
+    pub fn collect(mut self) -> Result<Vec<f32>> {
+        let mut samples = Vec::with_capacity(self.waveformer.output_frames() * constants::CHANNELS);
+        let mut block = Vec::new();
+        while self.next_block(&mut block)? {
+            samples.extend_from_slice(&block);
+        }
+        Ok(samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ruler, TimeSignature, WaveFormerBuilder};
+
+    fn streams_like_the_buffer(builder: WaveFormerBuilder) {
+        let waveformer = builder.build();
+        let buffered = waveformer.render_to_buffer().unwrap();
+        let streamed = waveformer.streaming().unwrap().collect().unwrap();
+        assert_eq!(streamed.len(), buffered.len());
+        assert_eq!(streamed, buffered);
+        assert_eq!(
+            buffered.len(),
+            waveformer.output_frames() * constants::CHANNELS
+        );
+    }
+
+    #[test]
+    fn padding_streams_like_the_buffer() {
+        let ruler = Ruler::default();
+        let bar = TimeSignature::new(3, 4);
+        streams_like_the_buffer(WaveFormerBuilder::new().duration(0.3).pad_to(1.0));
+        streams_like_the_buffer(WaveFormerBuilder::new().duration(1.0).pad_to(0.3));
+        streams_like_the_buffer(
+            WaveFormerBuilder::new()
+                .duration(0.3)
+                .offset(0.2)
+                .pad_to(0.1),
+        );
+        streams_like_the_buffer(
+            WaveFormerBuilder::new()
+                .duration(0.3)
+                .snap_to_bar(&ruler, bar),
+        );
+        streams_like_the_buffer(
+            WaveFormerBuilder::new()
+                .duration(0.3)
+                .pad_to(0.5)
+                .snap_to_bar(&ruler, bar)
+                .calibration_tone(true),
+        );
+    }
+
+    #[test]
+    fn whole_buffer_options_refuse_to_stream() {
+        for builder in [
+            WaveFormerBuilder::new().trim_silence(1e-4),
+            WaveFormerBuilder::new().oversample(2),
+        ] {
+            let waveformer = builder.build();
+            let error = waveformer.streaming().err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn joined_blocks_equal_the_one_shot_render() {
+        let waveformer = WaveFormerBuilder::new()
+            .duration(0.5)
+            .unison(3, 10.0)
+            .zero_align(true)
+            .build();
+        let mut renderer = waveformer.streaming().unwrap();
+        let (mut joined, mut block, mut blocks) = (Vec::new(), Vec::new(), 0);
+        while renderer.next_block(&mut block).unwrap() {
+            assert!(block.len() <= 2 * RENDER_BLOCK * constants::CHANNELS);
+            joined.extend_from_slice(&block);
+            blocks += 1;
+        }
+        assert!(block.is_empty() && !renderer.next_block(&mut block).unwrap());
+        assert!(blocks > 1);
+        assert!(joined == waveformer.render_to_buffer().unwrap());
+        streams_like_the_buffer(WaveFormerBuilder::new().duration(0.3).offset(0.1));
+    }
+
+    #[test]
+    fn two_half_blocks_equal_one_full_render() {
+        let waveformer = WaveFormerBuilder::new().duration(0.25).build();
+        let full = waveformer.render_to_buffer().unwrap();
+        let half = full.len() / 2 + 1;
+        let mut renderer = waveformer.streaming().unwrap();
+        let (mut first, mut second) = (vec![1.0; half], vec![1.0; half]);
+        renderer.fill_ring(&mut first).unwrap();
+        renderer.fill_ring(&mut second).unwrap();
+        let joined = [first, second].concat();
+        assert!(joined[..full.len()] == full[..]);
+        assert!(joined[full.len()..].iter().all(|&sample| sample == 0.0));
+    }
+}
@@ -0,0 +1,163 @@
+//! Duration and pitch changes of rendered buffers.
+
+use crate::{mix, resample};
+
+/// Length, in seconds, of the overlapping segments of [`time_stretch`].
+/// Segments must span a few periods of the lowest note; longer ones smear
+/// attacks.
+pub const STRETCH_WINDOW: f64 = 0.04;
+/// How far, in seconds, [`time_stretch`] may move a segment from its
+/// nominal position to line its waveform up with the previous one.
+pub const STRETCH_SEARCH: f64 = 0.01;
+
+/// Periodic Hann window; at 50% overlap the copies sum to one.
+fn hann(length: usize) -> Vec<f32> {
+    (0..length)
+        .map(|index| {
+            let x = index as f64 / length as f64;
+            (0.5 - 0.5 * (2.0 * std::f64::consts::PI * x).cos()) as f32
+        })
+        .collect()
+}
+
+/// Offset in `-search..=search` from `nominal` whose segment of `mono`
+/// best matches `template`, by cross-correlation.
+fn best_offset(mono: &[f32], template: &[f32], nominal: usize, search: usize) -> usize {
+    let last = mono.len().saturating_sub(template.len());
+    let start = nominal.saturating_sub(search).min(last);
+    let end = (nominal + search).min(last);
+    (start..=end)
+        .max_by(|&a, &b| {
+            let score = |position: usize| -> f32 {
+                template
+                    .iter()
+                    .zip(&mono[position..])
+                    .map(|(x, y)| x * y)
+                    .sum()
+            };
+            score(a).total_cmp(&score(b))
+        })
+        .unwrap_or(start)
+}
+
+/// Changes the length of an interleaved buffer by `factor` keeping its
+/// pitch, with waveform-similarity overlap-add (WSOLA): Hann-windowed
+/// segments of [`STRETCH_WINDOW`] are read at `1 / factor` of the rate
+/// they are written, each nudged by up to [`STRETCH_SEARCH`] so its phase
+/// continues the previous one. Steady tones come out clean. Transients
+/// get smeared or doubled, noise picks up a faint flutter, and factors far
+/// from 1 make both worse. Empty for a non-positive factor.
+pub fn time_stretch(samples: &[f32], channels: usize, factor: f64, sample_rate: usize) -> Vec<f32> {
+    let frames = samples.len() / channels;
+    if frames == 0 || !factor.is_finite() || factor <= 0.0 {
+        return Vec::new();
+    }
+    let out_frames = (frames as f64 * factor).round() as usize;
+    let window_length = ((STRETCH_WINDOW * sample_rate as f64) as usize).clamp(1, frames);
+    let hop = (window_length / 2).max(1);
+    let search = (STRETCH_SEARCH * sample_rate as f64) as usize;
+    let window = hann(window_length);
+    let mono = mix::to_mono(samples, channels);
+    let mut out = vec![0.0; (out_frames + window_length) * channels];
+    let mut weights = vec![0.0; out_frames + window_length];
+    let mut previous: Option<usize> = None;
+    for segment in 0..out_frames.div_ceil(hop) {
+        let nominal = ((segment * hop) as f64 / factor) as usize;
+        let position = match previous {
+            Some(previous) if previous + 2 * hop <= frames => best_offset(
+                &mono,
+                &mono[previous + hop..previous + 2 * hop],
+                nominal,
+                search,
+            ),
+            _ => nominal,
+        }
+        .min(frames - window_length);
+        previous = Some(position);
+        let target = segment * hop;
+        for (index, &weight) in window.iter().enumerate() {
+            let source = &samples[(position + index) * channels..(position + index + 1) * channels];
+            for (out, &sample) in out[(target + index) * channels..].iter_mut().zip(source) {
+                *out += sample * weight;
+            }
+            weights[target + index] += weight;
+        }
+    }
+    out.truncate(out_frames * channels);
+    for (frame, &weight) in out.chunks_exact_mut(channels).zip(&weights) {
+        if weight > 1e-3 {
+            frame.iter_mut().for_each(|sample| *sample /= weight);
+        }
+    }
+    out
+}
+
+/// Shifts the pitch of an interleaved buffer by `semitones` keeping its
+/// length: [`time_stretch`] by the pitch ratio, then
+/// [`resample::resample`] back to the original length. Shares the
+/// stretch's artifacts, and formants move with the pitch, so large shifts
+/// sound unnatural.
+pub fn pitch_shift(
+    samples: &[f32],
+    channels: usize,
+    semitones: f64,
+    sample_rate: usize,
+) -> Vec<f32> {
+    let ratio = 2.0_f64.powf(semitones / 12.0);
+    let stretched = time_stretch(samples, channels, ratio, sample_rate);
+    let from_rate = (sample_rate as f64 * ratio).round() as usize;
+    let mut shifted = resample::resample(
+        &stretched,
+        channels,
+        from_rate,
+        sample_rate,
+        resample::DEFAULT_SINC_TAPS,
+    );
+    shifted.resize(samples.len() / channels * channels, 0.0);
+    shifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis, constants, WaveFormerBuilder};
+
+    fn tone(frequency: f64) -> Vec<f32> {
+        WaveFormerBuilder::new()
+            .frequency(frequency)
+            .duration(0.5)
+            .build()
+            .render_to_buffer()
+            .unwrap()
+    }
+
+    /// Pitch of the middle of an interleaved buffer, away from the edges.
+    fn pitch(samples: &[f32]) -> f64 {
+        let mono = mix::to_mono(samples, constants::CHANNELS);
+        let quarter = mono.len() / 4;
+        analysis::detect_frequency(&mono[quarter..3 * quarter], constants::FRAME_RATE).unwrap()
+    }
+
+    #[test]
+    fn stretching_doubles_the_length_at_the_same_pitch() {
+        let original = tone(220.0);
+        let stretched = time_stretch(&original, constants::CHANNELS, 2.0, constants::FRAME_RATE);
+        assert_eq!(stretched.len(), 2 * original.len());
+        assert!(analysis::cents(pitch(&stretched), 220.0).abs() < 10.0);
+        assert!(
+            time_stretch(&original, constants::CHANNELS, 0.0, constants::FRAME_RATE).is_empty()
+        );
+    }
+
+    #[test]
+    fn an_octave_up_doubles_the_pitch_at_the_same_length() {
+        let original = tone(220.0);
+        let shifted = pitch_shift(&original, constants::CHANNELS, 12.0, constants::FRAME_RATE);
+        assert_eq!(shifted.len(), original.len());
+        assert!(
+            analysis::cents(pitch(&shifted), 440.0).abs() < 15.0,
+            "{}",
+            pitch(&shifted)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Block-by-block oscillator engine shared by every `WaveFormer` render path.
+
+use std::{
+    f64::consts::PI,
+    io::{Error, ErrorKind, Result},
+};
+
+use crate::{
+    constants, effects, envelope,
+    filters::Biquad,
+    loudness,
+    noise::Rng,
+    pan,
+    wave_former::{FILTER_ENVELOPE_Q, MIN_CUTOFF, NYQUIST_CLAMP},
+    WaveFormer,
+};
+
+struct UnisonVoice {
+    phase: f64,
+    /// Phase of the right channel under a binaural beat.
+    beat_phase: f64,
+    pan: f64,
+    lfo_rate: f64,
+    lfo_phase: f64,
+}
+
+impl UnisonVoice {
+    fn detune(&self, seconds: f64, depth: f64) -> f64 {
+        let cents = depth * (2.0 * PI * (self.lfo_rate * seconds + self.lfo_phase)).sin();
+        2.0_f64.powf(cents / 1200.0)
+    }
+}
+
+/// A single centred voice for `unison <= 1`, otherwise voices spread
+/// across the stereo field with seeded phases and LFOs.
+fn unison_voices(waveformer: &WaveFormer, waveform: &dyn Fn(f64) -> f64) -> Vec<UnisonVoice> {
+    let phase = waveformer.start_phase(waveform);
+    if waveformer.unison <= 1 {
+        return vec![UnisonVoice {
+            phase,
+            beat_phase: phase,
+            pan: 0.0,
+            lfo_rate: 0.0,
+            lfo_phase: 0.0,
+        }];
+    }
+    let mut rng = Rng::new(waveformer.seed);
+    (0..waveformer.unison)
+        .map(|voice| {
+            let phase = (phase + rng.next_f64()) % 1.0;
+            UnisonVoice {
+                phase,
+                beat_phase: phase,
+                pan: 2.0 * voice as f64 / (waveformer.unison - 1) as f64 - 1.0,
+                lfo_rate: 0.2 + 0.6 * rng.next_f64(),
+                lfo_phase: rng.next_f64(),
+            }
+        })
+        .collect()
+}
+
+/// Render state of one `WaveFormer`: oscillator phases, filter memories and
+/// the position in the render, so frames can be pulled in any block size.
+pub(crate) struct Synth<'a, F> {
+    waveformer: &'a WaveFormer,
+    gain: F,
+    /// Oscillator shape, the voice's `waveform` unless replaced.
+    waveform: Box<dyn Fn(f64) -> f64 + 'a>,
+    /// Frequency multiplier over time, for glides between notes.
+    pitch: Box<dyn Fn(f64) -> f64 + 'a>,
+    /// Base frequency after the Nyquist policy, `None` renders silence.
+    frequency: Option<f64>,
+    /// Amplitude including loudness compensation.
+    amplitude: f64,
+    frame: usize,
+    frames_count: usize,
+    voices: Vec<UnisonVoice>,
+    filters: Vec<Biquad>,
+}
+
+impl<'a, F: Fn(f64) -> f64> Synth<'a, F> {
+    pub(crate) fn new(waveformer: &'a WaveFormer, gain: F) -> Result<Self> {
+        waveformer.validate()?;
+        if waveformer.binaural_beat.is_some() && constants::CHANNELS != 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "binaural beats need stereo output",
+            ));
+        }
+        let filters = match waveformer.filter_envelope {
+            Some((_, base, _)) => {
+                vec![
+                    Biquad::low_pass(base, FILTER_ENVELOPE_Q, waveformer.sample_rate);
+                    constants::CHANNELS
+                ]
+            }
+            None => Vec::new(),
+        };
+        let frequency = waveformer.checked_frequency()?;
+        let mut amplitude = waveformer.amplitude;
+        if let (true, Some(frequency)) = (waveformer.equal_loudness, frequency) {
+            amplitude *= loudness::equal_loudness_gain(frequency);
+        }
+        Ok(Self {
+            waveformer,
+            gain,
+            waveform: Box::new(waveformer.waveform),
+            pitch: Box::new(|_| 1.0),
+            frequency,
+            amplitude,
+            frame: 0,
+            frames_count: waveformer.frame_count(),
+            voices: unison_voices(waveformer, &waveformer.waveform),
+            filters,
+        })
+    }
+    /// Multiplies the base frequency by `pitch(seconds)` on every frame;
+    /// the oscillators keep their phase, so the sweep is click-free.
+    pub(crate) fn with_pitch<P: Fn(f64) -> f64 + 'a>(mut self, pitch: P) -> Self {
+        self.pitch = Box::new(pitch);
+        self
+    }
+    /// Samples `waveform` instead of the voice's own; call it before the
+    /// first [`Synth::fill`], since the start phases follow the shape.
+    pub(crate) fn with_waveform<W: Fn(f64) -> f64 + 'a>(mut self, waveform: W) -> Self {
+        self.voices = unison_voices(self.waveformer, &waveform);
+        self.waveform = Box::new(waveform);
+        self
+    }
+    /// Appends up to `frames` interleaved frames to `out`, returning how
+    /// many were produced; 0 once the render is complete.
+    pub(crate) fn fill(&mut self, out: &mut Vec<f32>, frames: usize) -> usize {
+        let frames = frames.min(self.frames_count - self.frame);
+        let start = out.len();
+        match self.frequency {
+            Some(frequency) => {
+                for _ in 0..frames {
+                    self.next_frame(frequency, out);
+                }
+            }
+            None => {
+                out.resize(start + frames * constants::CHANNELS, 0.0);
+                self.frame += frames;
+            }
+        }
+        if let Some((curve, drive)) = self.waveformer.waveshape {
+            effects::waveshape(&mut out[start..], drive, curve);
+        }
+        frames
+    }
+    fn next_frame(&mut self, base_frequency: f64, out: &mut Vec<f32>) {
+        let waveformer = self.waveformer;
+        let seconds = waveformer.frame_to_duration(self.frame);
+        let step = 1.0 / waveformer.sample_rate as f64;
+        let mut level = self.amplitude * (self.gain)(seconds) * waveformer.tremolo_gain(seconds)
+            / self.voices.len() as f64;
+        if let Some(envelope) = &waveformer.envelope {
+            level *= envelope.gain(seconds, waveformer.duration);
+        }
+        if let Some(tau) = waveformer.decay {
+            level *= envelope::exponential(seconds, tau);
+        }
+        level *= envelope::fade(
+            seconds,
+            waveformer.duration,
+            waveformer.fade_in,
+            waveformer.fade_out,
+            waveformer.fade_curve,
+        );
+        let frequency =
+            waveformer.instant_frequency(base_frequency * (self.pitch)(seconds), seconds);
+        let position = waveformer.pan_position(seconds);
+        let mut frame_samples = [0.0; constants::CHANNELS];
+        for voice in &mut self.voices {
+            let sample = (self.waveform)(voice.phase) * level;
+            let beat_sample = match waveformer.binaural_beat {
+                Some(_) => (self.waveform)(voice.beat_phase) * level,
+                None => sample,
+            };
+            let pan = (voice.pan + position).clamp(-1.0, 1.0);
+            for (channel, out) in frame_samples.iter_mut().enumerate() {
+                let sample = if channel == 1 { beat_sample } else { sample };
+                *out += sample * pan::channel_gain(pan, channel, constants::CHANNELS);
+            }
+            let detune = voice.detune(seconds, waveformer.chorus_depth);
+            voice.phase = (voice.phase + frequency * detune * step).rem_euclid(1.0);
+            if let Some(beat) = waveformer.binaural_beat {
+                voice.beat_phase =
+                    (voice.beat_phase + (frequency + beat) * detune * step).rem_euclid(1.0);
+            }
+        }
+        if let Some((envelope, base, depth)) = &waveformer.filter_envelope {
+            let max_cutoff = waveformer.sample_rate as f64 / 2.0 * NYQUIST_CLAMP;
+            let cutoff = (base + depth * envelope.gain(seconds, waveformer.duration))
+                .clamp(MIN_CUTOFF, max_cutoff);
+            for (sample, filter) in frame_samples.iter_mut().zip(&mut self.filters) {
+                filter.retune(cutoff);
+                *sample = filter.process(*sample);
+            }
+        }
+        out.extend(frame_samples.iter().map(|&sample| sample as f32));
+        self.frame += 1;
+    }
+}
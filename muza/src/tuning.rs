@@ -0,0 +1,128 @@
+use std::{
+    io::Result,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    analysis,
+    batch::{self, OCTAVE_NOTES},
+    wav, Ruler,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningResult {
+    pub path: PathBuf,
+    /// Note the file is meant to hold.
+    pub note: i64,
+    pub expected: f64,
+    /// Detected pitch, `None` when no pitch could be found.
+    pub measured: Option<f64>,
+    /// Deviation of `measured` from `expected` in cents.
+    pub deviation: Option<f64>,
+    pub in_tune: bool,
+}
+
+/// Octave and degree from a batch file name such as `o[2] n[5] l[1].wav`.
+fn batch_position(path: &Path) -> Option<(i64, i64)> {
+    Some((batch::field(path, "o[")?, batch::field(path, "n[")?))
+}
+
+fn nearest_note(ruler: &Ruler, frequency: f64) -> i64 {
+    let length = ruler.rations.len() as i64;
+    let guess = (length as f64 * (frequency / ruler.frequency).log2()).round() as i64;
+    (guess - length..=guess + length)
+        .min_by(|&a, &b| {
+            let distance = |note| analysis::cents(frequency, ruler.frequency(note)).abs();
+            distance(a).total_cmp(&distance(b))
+        })
+        .unwrap_or(guess)
+}
+
+/// Measures the pitch of every WAV under `dir` and compares it with the
+/// note it should hold, flagging files off by more than `tolerance_cents`.
+///
+/// Files named like the octave batch (`o[..] n[..] ...`) are checked
+/// against their batch position, rendered `offset` notes below the ruler's
+/// reference as in [`batch::render_octave_batch`]. Other files are compared
+/// with the nearest ruler note.
+pub fn verify_tuning(
+    dir: &Path,
+    ruler: &Ruler,
+    offset: i64,
+    tolerance_cents: f64,
+) -> Result<Vec<TuningResult>> {
+    let mut files = Vec::new();
+    batch::wav_files(dir, &mut files)?;
+    files.sort();
+    let mut measurements = Vec::with_capacity(files.len());
+    for path in files {
+        let (samples, spec) = wav::read(&path)?;
+        let mono: Vec<f32> = samples
+            .iter()
+            .step_by(spec.channels as usize)
+            .copied()
+            .collect();
+        let measured = analysis::detect_frequency(&mono, spec.sample_rate as usize);
+        measurements.push((path, measured));
+    }
+    Ok(measurements
+        .into_iter()
+        .map(|(path, measured)| {
+            let note = match (batch_position(&path), measured) {
+                (Some((octave, degree)), _) => octave * OCTAVE_NOTES + degree - offset,
+                (_, Some(measured)) => nearest_note(ruler, measured),
+                _ => 0,
+            };
+            let expected = ruler.frequency(note);
+            let deviation = measured.map(|measured| analysis::cents(measured, expected));
+            TuningResult {
+                path,
+                note,
+                expected,
+                measured,
+                deviation,
+                in_tune: deviation.is_some_and(|deviation| deviation.abs() <= tolerance_cents),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing, WaveFormerBuilder};
+
+    #[test]
+    fn rendered_batch_is_in_tune() {
+        let root = testing::temp_path("tuning-batch");
+        let ruler = Ruler::default();
+        batch::render_octave_batch(&ruler, 1, 3, &[1], &root).unwrap();
+        let report = verify_tuning(&root, &ruler, 3, 5.0).unwrap();
+        assert_eq!(report.len(), OCTAVE_NOTES as usize);
+        for result in &report {
+            assert!(result.in_tune, "{:?}", result);
+            assert!(result.deviation.unwrap().abs() < 1.0);
+        }
+        // A whole-table shift is a regression, not a different offset.
+        let shifted = verify_tuning(&root, &ruler, 2, 5.0).unwrap();
+        assert!(shifted.iter().all(|result| !result.in_tune));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn other_files_match_the_nearest_note() {
+        let root = testing::temp_path("tuning-single");
+        std::fs::create_dir_all(&root).unwrap();
+        let ruler = Ruler::default();
+        WaveFormerBuilder::new()
+            .note(&ruler, 7)
+            .build()
+            .render(root.join("fifth.wav"))
+            .unwrap();
+        let report = verify_tuning(&root, &ruler, 0, 5.0).unwrap();
+        std::fs::remove_dir_all(root).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].note, 7);
+        assert!(report[0].in_tune);
+    }
+}
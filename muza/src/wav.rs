@@ -0,0 +1,365 @@
+use std::{
+    fs,
+    io::{self, Cursor, Write},
+    path::Path,
+};
+
+use crate::constants;
+
+pub fn spec() -> hound::WavSpec {
+    spec_with_rate(constants::FRAME_RATE)
+}
+
+pub fn spec_with_rate(sample_rate: usize) -> hound::WavSpec {
+    hound::WavSpec {
+        channels: constants::CHANNELS as u16,
+        sample_rate: sample_rate as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    }
+}
+
+/// Sample format of written WAV files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BitDepth {
+    /// 32-bit IEEE float, which keeps overs above full scale.
+    #[default]
+    Float32,
+    /// 16-bit integer PCM, clipped to full scale.
+    Int16,
+    /// 24-bit integer PCM, clipped to full scale.
+    Int24,
+}
+
+/// Bytes ahead of the samples in a 16-bit file from [`write_with_spec`].
+pub const WAV_HEADER_SIZE: usize = 44;
+/// Bytes ahead of the samples in a float or 24-bit file, which hound
+/// writes with a 40-byte `WAVE_FORMAT_EXTENSIBLE` fmt chunk.
+pub const EXTENSIBLE_HEADER_SIZE: usize = 68;
+
+impl BitDepth {
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            BitDepth::Float32 => 4,
+            BitDepth::Int16 => 2,
+            BitDepth::Int24 => 3,
+        }
+    }
+    pub fn header_size(self) -> usize {
+        match self {
+            BitDepth::Int16 => WAV_HEADER_SIZE,
+            BitDepth::Float32 | BitDepth::Int24 => EXTENSIBLE_HEADER_SIZE,
+        }
+    }
+    /// Size on disk of `frames` interleaved frames written in this format.
+    pub fn file_size(self, frames: usize) -> usize {
+        self.header_size() + frames * constants::CHANNELS * self.bytes_per_sample()
+    }
+    pub fn spec(self, sample_rate: usize) -> hound::WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            BitDepth::Float32 => (32, hound::SampleFormat::Float),
+            BitDepth::Int16 => (16, hound::SampleFormat::Int),
+            BitDepth::Int24 => (24, hound::SampleFormat::Int),
+        };
+        hound::WavSpec {
+            bits_per_sample,
+            sample_format,
+            ..spec_with_rate(sample_rate)
+        }
+    }
+}
+
+/// Sample encoding of [`write_raw_pcm`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PcmFormat {
+    /// 32-bit IEEE float, 4 bytes per sample.
+    #[default]
+    F32,
+    /// Signed 16-bit integer, 2 bytes per sample, clipped to `[-1, 1]`.
+    I16,
+}
+
+impl PcmFormat {
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::F32 => 4,
+            PcmFormat::I16 => 2,
+        }
+    }
+}
+
+pub(crate) fn to_io(error: hound::Error) -> io::Error {
+    match error {
+        hound::Error::IoError(error) => error,
+        error => io::Error::other(error),
+    }
+}
+
+/// Writes `samples` in the writer's format, scaling to full scale and
+/// clipping for integer formats.
+pub(crate) fn write_samples<W: Write + io::Seek>(
+    writer: &mut hound::WavWriter<W>,
+    samples: &[f32],
+) -> io::Result<()> {
+    let spec = writer.spec();
+    match spec.sample_format {
+        hound::SampleFormat::Float => {
+            for &sample in samples {
+                writer.write_sample(sample).map_err(to_io)?;
+            }
+        }
+        hound::SampleFormat::Int => {
+            let full_scale = ((1_i64 << (spec.bits_per_sample - 1)) - 1) as f64;
+            for &sample in samples {
+                let sample = (sample.clamp(-1.0, 1.0) as f64 * full_scale).round() as i32;
+                writer.write_sample(sample).map_err(to_io)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes interleaved samples as a float WAV with the default layout.
+pub fn write<S: AsRef<Path>>(path: S, samples: &[f32]) -> io::Result<()> {
+    write_with_spec(path, samples, spec())
+}
+
+pub fn write_with_spec<S: AsRef<Path>>(
+    path: S,
+    samples: &[f32],
+    spec: hound::WavSpec,
+) -> io::Result<()> {
+    let mut writer = hound::WavWriter::create(path, spec).map_err(to_io)?;
+    write_samples(&mut writer, samples)?;
+    writer.finalize().map_err(to_io)
+}
+
+/// Writes interleaved samples as headerless little-endian PCM. Readers
+/// must be told the format, channel count and rate out of band.
+pub fn write_raw_pcm<S: AsRef<Path>>(
+    path: S,
+    samples: &[f32],
+    format: PcmFormat,
+) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(samples.len() * format.bytes_per_sample());
+    for &sample in samples {
+        match format {
+            PcmFormat::F32 => bytes.extend_from_slice(&sample.to_le_bytes()),
+            PcmFormat::I16 => {
+                let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+                bytes.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+    fs::write(path, bytes)
+}
+
+/// Writes each region of an interleaved buffer that starts at an onset
+/// frame and runs to the next one, or to the end for the last, as
+/// `out_dir/slice_000.wav`, `slice_001.wav`, ... at the default frame
+/// rate. Onsets are sorted first; repeated ones and ones past the end are
+/// skipped.
+pub fn slice(samples: &[f32], channels: usize, onsets: &[usize], out_dir: &Path) -> io::Result<()> {
+    let frames = samples.len() / channels;
+    let mut onsets: Vec<_> = onsets
+        .iter()
+        .copied()
+        .filter(|&onset| onset < frames)
+        .collect();
+    onsets.sort_unstable();
+    onsets.dedup();
+    fs::create_dir_all(out_dir)?;
+    let spec = hound::WavSpec {
+        channels: channels as u16,
+        ..spec()
+    };
+    for (index, &start) in onsets.iter().enumerate() {
+        let end = onsets.get(index + 1).copied().unwrap_or(frames);
+        write_with_spec(
+            out_dir.join(format!("slice_{:03}.wav", index)),
+            &samples[start * channels..end * channels],
+            spec,
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads a WAV file as interleaved `f32` samples in `[-1, 1]`.
+pub fn read<S: AsRef<Path>>(path: S) -> io::Result<(Vec<f32>, hound::WavSpec)> {
+    let mut reader = hound::WavReader::open(path).map_err(to_io)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / scale))
+                .collect()
+        }
+    };
+    Ok((samples.map_err(to_io)?, spec))
+}
+
+/// Writes a complete WAV stream to `writer`, e.g. stdout. The stream is
+/// built in memory first: the header stores sizes that are only known once
+/// every sample is written, and pipes cannot seek back to fill them in.
+pub fn write_to_writer<W: Write>(
+    mut writer: W,
+    samples: &[f32],
+    spec: hound::WavSpec,
+) -> io::Result<()> {
+    let mut bytes = Cursor::new(Vec::new());
+    let mut wav = hound::WavWriter::new(&mut bytes, spec).map_err(to_io)?;
+    write_samples(&mut wav, samples)?;
+    wav.finalize().map_err(to_io)?;
+    writer.write_all(bytes.get_ref())?;
+    writer.flush()
+}
+
+/// Appends a RIFF chunk to a finished WAV file and fixes the RIFF size.
+pub fn append_chunk<S: AsRef<Path>>(path: S, id: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut bytes = fs::read(&path)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a RIFF file",
+        ));
+    }
+    bytes.extend_from_slice(id);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        bytes.push(0);
+    }
+    let riff_size = (bytes.len() - 8) as u32;
+    bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    fs::write(path, bytes)
+}
+
+/// Finds the first chunk with `id` in a WAV file.
+pub fn read_chunk<S: AsRef<Path>>(path: S, id: &[u8; 4]) -> io::Result<Option<Vec<u8>>> {
+    let bytes = fs::read(path)?;
+    let mut position = 12;
+    while position + 8 <= bytes.len() {
+        let size = u32::from_le_bytes(bytes[position + 4..position + 8].try_into().unwrap());
+        let start = position + 8;
+        let end = (start + size as usize).min(bytes.len());
+        if &bytes[position..position + 4] == id {
+            return Ok(Some(bytes[start..end].to_vec()));
+        }
+        position = end + (size as usize % 2);
+    }
+    Ok(None)
+}
+
+/// Adds a `cue ` chunk with one cue point per frame offset.
+pub fn write_cue_points<S: AsRef<Path>>(path: S, frames: &[usize]) -> io::Result<()> {
+    let mut data = Vec::with_capacity(4 + 24 * frames.len());
+    data.extend_from_slice(&(frames.len() as u32).to_le_bytes());
+    for (id, &frame) in frames.iter().enumerate() {
+        let frame = (frame as u32).to_le_bytes();
+        data.extend_from_slice(&(id as u32 + 1).to_le_bytes());
+        data.extend_from_slice(&frame);
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&frame);
+    }
+    append_chunk(path, b"cue ", &data)
+}
+
+/// Bytes [`write_cue_points`] appends for `points` cue points.
+pub fn cue_chunk_size(points: usize) -> usize {
+    8 + 4 + 24 * points
+}
+
+/// Frame offsets of the cue points in a WAV file, empty without a `cue ` chunk.
+pub fn read_cue_points<S: AsRef<Path>>(path: S) -> io::Result<Vec<usize>> {
+    let Some(data) = read_chunk(path, b"cue ")? else {
+        return Ok(Vec::new());
+    };
+    Ok(data
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(24)
+        .map(|point| u32::from_le_bytes(point[20..24].try_into().unwrap()) as usize)
+        .collect())
+}
+
+/// Adds a `smpl` chunk with one forward loop from `start` to `end`, both
+/// frame offsets and `end` the last frame played before jumping back.
+pub fn write_loop_points<S: AsRef<Path>>(path: S, start: usize, end: usize) -> io::Result<()> {
+    let sample_rate = read_chunk(&path, b"fmt ")?
+        .and_then(|fmt| Some(u32::from_le_bytes(fmt.get(4..8)?.try_into().unwrap())))
+        .filter(|&rate| rate > 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing fmt chunk"))?;
+    let mut data = Vec::with_capacity(60);
+    for field in [0, 0, 1_000_000_000 / sample_rate, 60, 0, 0, 0, 1, 0] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [0, 0, start as u32, end as u32, 0, 0] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    append_chunk(path, b"smpl", &data)
+}
+
+/// The first loop of a WAV file's `smpl` chunk as `(start, end)` frames.
+pub fn read_loop_points<S: AsRef<Path>>(path: S) -> io::Result<Option<(usize, usize)>> {
+    let Some(data) = read_chunk(path, b"smpl")? else {
+        return Ok(None);
+    };
+    let field = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    if field(28) == Some(0) {
+        return Ok(None);
+    }
+    Ok(field(44).zip(field(48)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slices_join_back_into_the_region_after_the_first_onset() {
+        let dir = crate::testing::temp_path("slices");
+        let samples: Vec<f32> = (0..200).map(|index| index as f32 / 200.0).collect();
+        slice(&samples, 2, &[60, 20], &dir).unwrap();
+        let first = read(dir.join("slice_000.wav")).unwrap().0;
+        let second = read(dir.join("slice_001.wav")).unwrap().0;
+        assert!(!dir.join("slice_002.wav").exists());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(first.len(), 40 * 2);
+        assert_eq!([first, second].concat(), &samples[20 * 2..]);
+    }
+
+    #[test]
+    fn full_scale_24_bit_peaks_just_below_the_limit() {
+        let path = crate::testing::temp_path("24-bit.wav");
+        let mut samples: Vec<f32> = (0..4_800)
+            .map(|frame| (frame as f32 / 4_800.0 * 20.0 * std::f32::consts::PI).sin())
+            .collect();
+        samples.extend([1.5, -1.5]);
+        write_with_spec(&path, &samples, BitDepth::Int24.spec(constants::FRAME_RATE)).unwrap();
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let spec = reader.spec();
+        let values: Vec<i32> = reader.samples::<i32>().map(Result::unwrap).collect();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            (spec.bits_per_sample, spec.sample_format),
+            (24, hound::SampleFormat::Int)
+        );
+        let limit = (1 << 23) - 1;
+        let peak = values[..4_800]
+            .iter()
+            .map(|value| value.abs())
+            .max()
+            .unwrap();
+        assert!(peak <= limit && peak as f64 > 0.999 * limit as f64);
+        assert_eq!(values[4_800..], [limit, -limit]);
+    }
+}
@@ -0,0 +1,1571 @@
+use std::{
+    f64::consts::PI,
+    io::{Error, ErrorKind, Result, Write},
+    path::Path,
+};
+
+use crate::{
+    constants,
+    effects::{self, ShapeCurve},
+    envelope::{Adsr, FadeCurve},
+    pan, resample, ruler,
+    streaming::StreamingRenderer,
+    synth::Synth,
+    wav, wave_forms, NoteValue, Ruler, TimeSignature, WaveForm,
+};
+
+pub struct WaveFormerBuilder {
+    waveform: Option<WaveForm>,
+    duration: Option<f64>,
+    frequency: Option<f64>,
+    amplitude: Option<f64>,
+    sample_rate: Option<usize>,
+    envelope: Option<Adsr>,
+    pitch_envelope: Option<(Adsr, f64)>,
+    unison: Option<usize>,
+    chorus_depth: Option<f64>,
+    seed: Option<u64>,
+    waveshape: Option<(ShapeCurve, f32)>,
+    phase: Option<f64>,
+    zero_align: Option<bool>,
+    nyquist: Option<NyquistPolicy>,
+    pan: Option<f64>,
+    auto_pan: Option<(f64, f64)>,
+    filter_envelope: Option<(Adsr, f64, f64)>,
+    fade: Option<(f64, f64)>,
+    fade_curve: Option<FadeCurve>,
+    equal_loudness: Option<bool>,
+    trim_silence: Option<f32>,
+    pad_to: Option<f64>,
+    oversample: Option<usize>,
+    vibrato: Option<(f64, f64)>,
+    tremolo: Option<(f64, f64)>,
+    offset: Option<f64>,
+    decay: Option<f64>,
+    calibration_tone: Option<bool>,
+    frequency_policy: Option<FrequencyPolicy>,
+    bit_depth: Option<wav::BitDepth>,
+    bar: Option<f64>,
+    binaural_beat: Option<f64>,
+    strict: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WaveFormer {
+    pub waveform: WaveForm,
+    pub duration: f64,
+    pub frequency: f64,
+    pub amplitude: f64,
+    pub sample_rate: usize,
+    pub envelope: Option<Adsr>,
+    /// Bends the pitch by `pitch_env_depth` semitones at full envelope level.
+    pub pitch_envelope: Option<Adsr>,
+    pub pitch_env_depth: f64,
+    /// Number of layered oscillator copies; 1 renders a single plain voice.
+    pub unison: usize,
+    /// Peak detune, in cents, of each unison voice's slow LFO.
+    pub chorus_depth: f64,
+    /// Seed for every randomized option, so renders are reproducible.
+    pub seed: u64,
+    /// Waveshaping curve and drive applied to the finished buffer.
+    pub waveshape: Option<(ShapeCurve, f32)>,
+    /// Starting phase of the oscillator in cycles, `[0, 1)`.
+    pub phase: f64,
+    /// Starts on the waveform's zero crossing, overriding `phase`, and
+    /// trims the tail so the last frame is the one nearest to zero.
+    pub zero_align: bool,
+    pub nyquist: NyquistPolicy,
+    /// Stereo position in `[-1, 1]`, left to right.
+    pub pan: f64,
+    /// Rate, in Hz, of the sine LFO sweeping `pan`.
+    pub auto_pan_rate: f64,
+    /// Swing of the auto-pan LFO in `[0, 1]`; 0 keeps the position static.
+    pub auto_pan_depth: f64,
+    /// Resonant low-pass whose cutoff is `base + depth * envelope` Hz, as
+    /// `(envelope, base, depth)`.
+    pub filter_envelope: Option<(Adsr, f64, f64)>,
+    /// Fade-in length in seconds.
+    pub fade_in: f64,
+    /// Fade-out length in seconds.
+    pub fade_out: f64,
+    pub fade_curve: FadeCurve,
+    /// Scales the amplitude by [`crate::loudness::equal_loudness_gain`].
+    pub equal_loudness: bool,
+    /// Cuts trailing frames whose every sample is below this level.
+    pub trim_silence: Option<f32>,
+    /// Zero-pads or cuts the render to exactly this many seconds, after
+    /// `trim_silence`.
+    pub pad_to: Option<f64>,
+    /// Renders at this multiple of `sample_rate`, including waveshaping,
+    /// then decimates back, so nonlinear stages alias less. 1 is off.
+    pub oversample: usize,
+    /// Rate, in Hz, of the sine LFO bending the pitch; see
+    /// [`crate::NoteValue`] for tempo-synced rates.
+    pub vibrato_rate: f64,
+    /// Peak pitch swing of the vibrato in cents.
+    pub vibrato_depth: f64,
+    /// Rate, in Hz, of the sine LFO dipping the level.
+    pub tremolo_rate: f64,
+    /// Deepest dip of the tremolo in `[0, 1]`; 1 swings down to silence.
+    pub tremolo_depth: f64,
+    /// Seconds of leading silence before the oscillator starts.
+    pub offset: f64,
+    /// Time constant, in seconds, of a plain exponential decay from full
+    /// level, a lighter alternative to an ADSR for blips.
+    pub decay: Option<f64>,
+    /// Prepends [`CALIBRATION_DURATION`] seconds of a
+    /// [`CALIBRATION_FREQUENCY`] sine peaking at [`CALIBRATION_LEVEL`].
+    pub calibration_tone: bool,
+    pub frequency_policy: FrequencyPolicy,
+    /// Sample format of rendered WAV files.
+    pub bit_depth: wav::BitDepth,
+    /// Bar length in seconds; the render is zero-padded up to a whole
+    /// number of bars, after `pad_to`, so it loops on the beat grid.
+    pub bar: Option<f64>,
+    /// Renders the right channel `beat` Hz above the left, which sits at
+    /// `frequency`, so listeners on headphones hear a binaural beat.
+    /// Stereo only.
+    pub binaural_beat: Option<f64>,
+    /// Fails the render with the frame index of the first NaN or infinite
+    /// sample instead of writing it out. On by default with the `strict`
+    /// feature, for development builds.
+    pub strict: bool,
+}
+
+const ZERO_SEARCH_STEPS: usize = 4096;
+
+/// Fade-out, in seconds, of [`WaveFormer::render_preview`].
+pub const PREVIEW_FADE: f64 = 0.01;
+/// Frames per block of [`WaveFormer::render_streaming`].
+pub const RENDER_BLOCK: usize = 4096;
+/// Reference tone of the `calibration_tone` option: 1 kHz at -18 dBFS,
+/// a peak of `10^(-18 / 20)` ≈ 0.1259 on every channel, for one second.
+pub const CALIBRATION_FREQUENCY: f64 = 1_000.0;
+pub const CALIBRATION_LEVEL: f64 = 0.125_892_541_179_416_7;
+pub const CALIBRATION_DURATION: f64 = 1.0;
+/// Resonance of the envelope-swept low-pass.
+pub const FILTER_ENVELOPE_Q: f64 = 4.0;
+/// Lowest cutoff the filter envelope sweeps down to.
+pub(crate) const MIN_CUTOFF: f64 = 10.0;
+/// Fraction of the Nyquist limit that [`NyquistPolicy::Clamp`] renders at.
+pub(crate) const NYQUIST_CLAMP: f64 = 0.99;
+
+/// What to do when `frequency` is at or above half the frame rate, where a
+/// naive oscillator silently aliases to a lower pitch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NyquistPolicy {
+    /// Render as requested and print a warning.
+    #[default]
+    Warn,
+    /// Render just below the Nyquist limit instead.
+    Clamp,
+    /// Render silence of the requested length.
+    Silence,
+    /// Fail the render with [`ErrorKind::InvalidInput`].
+    Error,
+}
+
+/// What to do with a zero or negative `frequency`. A zero frequency never
+/// advances the phase, so it would render a DC line at `waveform(phase)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FrequencyPolicy {
+    /// Render zero as silence, and negative frequencies at their absolute
+    /// pitch with the phase running backwards, i.e. the waveform mirrored
+    /// in time.
+    #[default]
+    Lenient,
+    /// Fail the render with [`ErrorKind::InvalidInput`] for either.
+    Strict,
+}
+
+/// Errors with the frame index of the first non-finite sample.
+pub(crate) fn check_finite(samples: &[f32], first_frame: usize) -> Result<()> {
+    match samples.iter().position(|sample| !sample.is_finite()) {
+        Some(index) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "non-finite sample {} at frame {}",
+                samples[index],
+                first_frame + index / constants::CHANNELS
+            ),
+        )),
+        None => Ok(()),
+    }
+}
+
+impl WaveFormer {
+    pub fn duration_to_frame(&self, duration: f64) -> usize {
+        (duration * self.sample_rate as f64) as usize
+    }
+    pub fn frame_to_duration(&self, frame: usize) -> f64 {
+        frame as f64 / self.sample_rate as f64
+    }
+    /// Frames of oscillator output, before zero alignment trims the tail
+    /// and `offset`, `trim_silence` or `pad_to` change the length.
+    pub fn frame_count(&self) -> usize {
+        self.duration_to_frame(self.duration)
+    }
+    /// Frames of [`WaveFormer::render_to_buffer`], before `trim_silence`
+    /// shortens it.
+    pub fn output_frames(&self) -> usize {
+        self.padded_frames(self.frame_count() + self.duration_to_frame(self.offset))
+            + self.calibration_frames()
+    }
+    /// Frames of the leading calibration tone, 0 without one.
+    pub(crate) fn calibration_frames(&self) -> usize {
+        if self.calibration_tone {
+            self.duration_to_frame(CALIBRATION_DURATION)
+        } else {
+            0
+        }
+    }
+    /// Length that `frames` of offset silence and oscillator output take
+    /// once `pad_to` and `snap_to_bar` have applied.
+    pub(crate) fn padded_frames(&self, frames: usize) -> usize {
+        let mut frames = self
+            .pad_to
+            .map_or(frames, |duration| self.duration_to_frame(duration));
+        if let Some(bar) = self.bar {
+            let snapped = self.duration_to_frame(ruler::snap(self.frame_to_duration(frames), bar));
+            frames = frames.max(snapped);
+        }
+        frames
+    }
+    /// Size of the file [`WaveFormer::render`] writes, header included; an
+    /// upper bound when `trim_silence` is set.
+    pub fn estimated_bytes(&self) -> usize {
+        self.bit_depth.file_size(self.output_frames())
+    }
+    pub fn spec(&self) -> hound::WavSpec {
+        self.bit_depth.spec(self.sample_rate)
+    }
+    /// Rejects non-finite amplitude, frequency or duration, which would
+    /// otherwise fill the whole buffer with NaN.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("amplitude", self.amplitude),
+            ("frequency", self.frequency),
+            ("duration", self.duration),
+        ] {
+            if !value.is_finite() {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} must be finite, got {}", name, value),
+                ));
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn instant_frequency(&self, frequency: f64, seconds: f64) -> f64 {
+        let mut cents = self.vibrato_depth * (2.0 * PI * self.vibrato_rate * seconds).sin();
+        if let Some(envelope) = &self.pitch_envelope {
+            cents += 100.0 * self.pitch_env_depth * envelope.gain(seconds, self.duration);
+        }
+        frequency * 2.0_f64.powf(cents / 1200.0)
+    }
+    pub(crate) fn tremolo_gain(&self, seconds: f64) -> f64 {
+        let sweep = (2.0 * PI * self.tremolo_rate * seconds).sin();
+        1.0 - self.tremolo_depth * (1.0 + sweep) / 2.0
+    }
+    pub(crate) fn pan_position(&self, seconds: f64) -> f64 {
+        let sweep = (2.0 * PI * self.auto_pan_rate * seconds).sin();
+        (self.pan + self.auto_pan_depth * sweep).clamp(-1.0, 1.0)
+    }
+    /// The frequency to render after applying `frequency_policy` and
+    /// `nyquist`; `None` means silence.
+    pub(crate) fn checked_frequency(&self) -> Result<Option<f64>> {
+        if self.frequency <= 0.0 {
+            match self.frequency_policy {
+                FrequencyPolicy::Strict => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("frequency must be positive, got {}", self.frequency),
+                    ));
+                }
+                FrequencyPolicy::Lenient if self.frequency == 0.0 => return Ok(None),
+                FrequencyPolicy::Lenient => {}
+            }
+        }
+        let nyquist = self.sample_rate as f64 / 2.0;
+        if self.frequency.abs() < nyquist {
+            return Ok(Some(self.frequency));
+        }
+        match self.nyquist {
+            NyquistPolicy::Warn => {
+                eprintln!(
+                    "warning: {} Hz is above the {} Hz Nyquist limit and will alias",
+                    self.frequency, nyquist
+                );
+                Ok(Some(self.frequency))
+            }
+            NyquistPolicy::Clamp => Ok(Some(nyquist * NYQUIST_CLAMP * self.frequency.signum())),
+            NyquistPolicy::Silence => Ok(None),
+            NyquistPolicy::Error => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} Hz is above the {} Hz Nyquist limit",
+                    self.frequency, nyquist
+                ),
+            )),
+        }
+    }
+    /// Phase to start `waveform` at: the configured one, or its sample
+    /// closest to zero under `zero_align`.
+    pub(crate) fn start_phase(&self, waveform: &dyn Fn(f64) -> f64) -> f64 {
+        if !self.zero_align {
+            return self.phase;
+        }
+        (0..ZERO_SEARCH_STEPS)
+            .map(|step| step as f64 / ZERO_SEARCH_STEPS as f64)
+            .min_by(|a, b| waveform(*a).abs().total_cmp(&waveform(*b).abs()))
+            .unwrap_or(self.phase)
+    }
+    /// Cuts the buffer after the frame closest to zero within the last period.
+    pub(crate) fn align_tail(&self, samples: &mut Vec<f32>) {
+        let channels = constants::CHANNELS;
+        let frames_count = samples.len() / channels;
+        let period = (self.sample_rate as f64 / self.frequency.abs()).ceil() as usize;
+        if self.frequency == 0.0 || period >= frames_count {
+            return;
+        }
+        let last = (frames_count - period..frames_count)
+            .min_by(|&a, &b| {
+                samples[a * channels]
+                    .abs()
+                    .total_cmp(&samples[b * channels].abs())
+            })
+            .unwrap_or(frames_count - 1);
+        samples.truncate((last + 1) * channels);
+    }
+    /// Renders interleaved samples for every channel.
+    pub fn render_to_buffer(&self) -> Result<Vec<f32>> {
+        self.render_to_buffer_with_gain(|_| 1.0)
+    }
+    /// Like [`WaveFormer::render_to_buffer`], multiplying every frame by
+    /// `gain(seconds)` on top of the amplitude and envelope.
+    pub fn render_to_buffer_with_gain<F: Fn(f64) -> f64>(&self, gain: F) -> Result<Vec<f32>> {
+        self.render_to_buffer_with_pitch(gain, |_| 1.0)
+    }
+    /// Like [`WaveFormer::render_to_buffer`], sampling `waveform` instead of
+    /// the `waveform` field, for generators that capture state such as
+    /// [`wave_forms::additive`], which a [`WaveForm`] pointer can't hold.
+    pub fn render_to_buffer_with_waveform<W: Fn(f64) -> f64>(
+        &self,
+        waveform: W,
+    ) -> Result<Vec<f32>> {
+        self.render_to_buffer_with(|_| 1.0, |_| 1.0, &waveform)
+    }
+    /// Like [`WaveFormer::render_to_buffer_with_gain`], also multiplying the
+    /// frequency by `pitch(seconds)`.
+    pub(crate) fn render_to_buffer_with_pitch<F: Fn(f64) -> f64, P: Fn(f64) -> f64>(
+        &self,
+        gain: F,
+        pitch: P,
+    ) -> Result<Vec<f32>> {
+        self.render_to_buffer_with(gain, pitch, &self.waveform)
+    }
+    fn render_to_buffer_with<F: Fn(f64) -> f64, P: Fn(f64) -> f64>(
+        &self,
+        gain: F,
+        pitch: P,
+        waveform: &dyn Fn(f64) -> f64,
+    ) -> Result<Vec<f32>> {
+        if self.oversample > 1 {
+            let rate = self.sample_rate * self.oversample;
+            let oversampled = WaveFormer {
+                sample_rate: rate,
+                oversample: 1,
+                ..self.clone()
+            }
+            .render_to_buffer_with(gain, pitch, waveform)?;
+            return Ok(resample::decimate(
+                &oversampled,
+                constants::CHANNELS,
+                self.oversample,
+                rate,
+            ));
+        }
+        let mut synth = Synth::new(self, gain)?
+            .with_pitch(pitch)
+            .with_waveform(waveform);
+        let mut samples = Vec::with_capacity(self.frame_count() * constants::CHANNELS);
+        synth.fill(&mut samples, usize::MAX);
+        if self.zero_align {
+            self.align_tail(&mut samples);
+        }
+        if let Some(threshold) = self.trim_silence {
+            effects::trim_trailing_silence(&mut samples, threshold, constants::CHANNELS);
+        }
+        let silence = self.duration_to_frame(self.offset) * constants::CHANNELS;
+        samples.splice(0..0, std::iter::repeat_n(0.0, silence));
+        let frames = self.padded_frames(samples.len() / constants::CHANNELS);
+        samples.resize(frames * constants::CHANNELS, 0.0);
+        if self.calibration_tone {
+            samples.splice(0..0, self.render_calibration()?);
+        }
+        if self.strict {
+            check_finite(&samples, 0)?;
+        }
+        Ok(samples)
+    }
+    /// True when only waveform, frequency, amplitude, phase and static pan
+    /// shape the render, so [`WaveFormer::render_to_buffer_f32`] can take
+    /// its fast path.
+    fn is_plain(&self) -> bool {
+        self.envelope.is_none()
+            && self.pitch_envelope.is_none()
+            && self.unison <= 1
+            && self.waveshape.is_none()
+            && !self.zero_align
+            && self.auto_pan_depth == 0.0
+            && self.filter_envelope.is_none()
+            && self.fade_in == 0.0
+            && self.fade_out == 0.0
+            && !self.equal_loudness
+            && self.trim_silence.is_none()
+            && self.pad_to.is_none()
+            && self.oversample <= 1
+            && self.offset == 0.0
+            && self.vibrato_depth == 0.0
+            && self.tremolo_depth == 0.0
+            && self.decay.is_none()
+            && !self.calibration_tone
+            && self.bar.is_none()
+            && self.binaural_beat.is_none()
+    }
+    /// Renders plain tones with the oscillator phase, levels and built-in
+    /// waveforms in `f32`, for targets where double precision is slow.
+    /// Custom waveforms are still evaluated in `f64`, as [`WaveForm`]
+    /// requires. Only waveform, frequency, amplitude, phase and static pan
+    /// take this path: envelopes, fades and every other option fall back
+    /// to [`WaveFormer::render_to_buffer`]. The `f32` phase drifts from the
+    /// exact one, so the error grows with the length: a 440 Hz sine stays
+    /// within about `2e-3 * amplitude` of the `f64` render after one
+    /// second and `2e-2 * amplitude` after ten. `examples/f32_speed.rs`
+    /// times both paths.
+    pub fn render_to_buffer_f32(&self) -> Result<Vec<f32>> {
+        self.validate()?;
+        let frequency = match self.checked_frequency()? {
+            Some(frequency) if self.is_plain() => frequency,
+            _ => return self.render_to_buffer(),
+        };
+        let gains: [f32; constants::CHANNELS] = std::array::from_fn(|channel| {
+            (self.amplitude * pan::channel_gain(self.pan, channel, constants::CHANNELS)) as f32
+        });
+        let increment = (frequency / self.sample_rate as f64) as f32;
+        let mut phase = self.phase as f32;
+        let single = wave_forms::single_precision(self.waveform);
+        let mut samples = Vec::with_capacity(self.frame_count() * constants::CHANNELS);
+        for _ in 0..self.frame_count() {
+            let value = match single {
+                Some(waveform) => waveform(phase),
+                None => (self.waveform)(phase as f64) as f32,
+            };
+            samples.extend(gains.iter().map(|gain| gain * value));
+            phase = (phase + increment).rem_euclid(1.0);
+        }
+        if self.strict {
+            check_finite(&samples, 0)?;
+        }
+        Ok(samples)
+    }
+    pub(crate) fn render_calibration(&self) -> Result<Vec<f32>> {
+        WaveFormerBuilder::new()
+            .frequency(CALIBRATION_FREQUENCY)
+            .duration(CALIBRATION_DURATION)
+            .amplitude(CALIBRATION_LEVEL)
+            .sample_rate(self.sample_rate)
+            .build()
+            .render_to_buffer()
+    }
+    pub fn render<S: AsRef<Path>>(&mut self, path: S) -> Result<()> {
+        wav::write_with_spec(path, &self.render_to_buffer()?, self.spec())
+    }
+    /// Renders at most `preview` seconds, fading the cut end out over
+    /// [`PREVIEW_FADE`] so the preview doesn't click.
+    pub fn render_preview<S: AsRef<Path>>(&mut self, preview: f64, path: S) -> Result<()> {
+        let duration = self.duration.min(preview);
+        let mut waveformer = WaveFormer {
+            duration,
+            fade_out: self.fade_out.max(PREVIEW_FADE).min(duration),
+            ..self.clone()
+        };
+        waveformer.render(path)
+    }
+    /// Renders interleaved little-endian samples with no header; see
+    /// [`wav::write_raw_pcm`].
+    pub fn render_raw_pcm<S: AsRef<Path>>(
+        &mut self,
+        path: S,
+        format: wav::PcmFormat,
+    ) -> Result<()> {
+        wav::write_raw_pcm(path, &self.render_to_buffer()?, format)
+    }
+    /// Renders a WAV stream into `writer`; see [`wav::write_to_writer`].
+    pub fn render_to_writer<W: Write>(&mut self, writer: W) -> Result<()> {
+        wav::write_to_writer(writer, &self.render_to_buffer()?, self.spec())
+    }
+    /// Renders straight to disk block by block through a
+    /// [`StreamingRenderer`], so memory stays bounded however long the
+    /// render is. Returns the number of blocks written.
+    pub fn render_streaming<S: AsRef<Path>>(&mut self, path: S) -> Result<usize> {
+        let mut renderer = self.streaming()?;
+        let mut writer = hound::WavWriter::create(path, self.spec()).map_err(wav::to_io)?;
+        let mut block = Vec::with_capacity(RENDER_BLOCK * constants::CHANNELS);
+        let mut blocks = 0;
+        while renderer.next_block(&mut block)? {
+            wav::write_samples(&mut writer, &block)?;
+            blocks += 1;
+        }
+        writer.finalize().map_err(wav::to_io)?;
+        Ok(blocks)
+    }
+    /// A block renderer for this voice; see [`StreamingRenderer`].
+    pub fn streaming(&self) -> Result<StreamingRenderer<'_>> {
+        StreamingRenderer::new(self)
+    }
+    /// Renders with a pre-baked gain curve stretched over the whole
+    /// duration: `gain[0]` at the start, the last value at the end and
+    /// linear interpolation in between, whatever the curve's length.
+    pub fn render_with_envelope<S: AsRef<Path>>(&mut self, gain: &[f32], path: S) -> Result<()> {
+        if gain.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "gain envelope must not be empty",
+            ));
+        }
+        let duration = self.duration;
+        let last = gain.len() - 1;
+        self.render_with_gain(
+            |seconds| {
+                let position = if duration > 0.0 {
+                    (seconds / duration).clamp(0.0, 1.0) * last as f64
+                } else {
+                    0.0
+                };
+                let index = (position as usize).min(last);
+                let next = (index + 1).min(last);
+                let fraction = position - index as f64;
+                gain[index] as f64 * (1.0 - fraction) + gain[next] as f64 * fraction
+            },
+            path,
+        )
+    }
+    /// Renders with a `smpl` loop around `loop_start..=loop_end` (frames).
+    /// Each boundary moves to the nearest rising zero crossing within a
+    /// period so the jump back doesn't click; returns the snapped frames.
+    pub fn render_looped<S: AsRef<Path>>(
+        &mut self,
+        loop_start: usize,
+        loop_end: usize,
+        path: S,
+    ) -> Result<(usize, usize)> {
+        let samples = self.render_to_buffer()?;
+        let frames_count = samples.len() / constants::CHANNELS;
+        if loop_start >= loop_end || loop_end >= frames_count {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "loop {}..={} must be ordered and within {} frames",
+                    loop_start, loop_end, frames_count
+                ),
+            ));
+        }
+        let start = self.nearest_crossing(&samples, loop_start);
+        // The frame before a crossing is the last one of the loop, so the
+        // jump lands on the crossing at `start` without repeating it.
+        let end = self
+            .nearest_crossing(&samples, loop_end + 1)
+            .saturating_sub(1);
+        let (start, end) = if start < end {
+            (start, end)
+        } else {
+            (loop_start, loop_end)
+        };
+        wav::write_with_spec(&path, &samples, self.spec())?;
+        wav::write_loop_points(&path, start, end)?;
+        Ok((start, end))
+    }
+    /// The rising zero crossing closest to `frame` within one period,
+    /// falling back to the quietest frame there.
+    fn nearest_crossing(&self, samples: &[f32], frame: usize) -> usize {
+        let channels = constants::CHANNELS;
+        let frames_count = samples.len() / channels;
+        let period = if self.frequency == 0.0 {
+            1
+        } else {
+            (self.sample_rate as f64 / self.frequency.abs()).ceil() as usize
+        };
+        let window = frame.saturating_sub(period).max(1)..(frame + period).min(frames_count);
+        let sample = |frame: usize| samples[frame * channels];
+        window
+            .clone()
+            .filter(|&candidate| sample(candidate - 1) < 0.0 && sample(candidate) >= 0.0)
+            .min_by_key(|&candidate| candidate.abs_diff(frame))
+            .or_else(|| window.min_by(|&a, &b| sample(a).abs().total_cmp(&sample(b).abs())))
+            .unwrap_or(frame)
+    }
+    /// Renders with a time-varying `gain(seconds)`, e.g. tremolo or swells.
+    pub fn render_with_gain<F: Fn(f64) -> f64, S: AsRef<Path>>(
+        &mut self,
+        gain: F,
+        path: S,
+    ) -> Result<()> {
+        wav::write_with_spec(path, &self.render_to_buffer_with_gain(gain)?, self.spec())
+    }
+}
+
+impl Default for WaveFormerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaveFormerBuilder {
+    pub fn new() -> Self {
+        Self {
+            waveform: None,
+            duration: None,
+            frequency: None,
+            amplitude: None,
+            sample_rate: None,
+            envelope: None,
+            pitch_envelope: None,
+            unison: None,
+            chorus_depth: None,
+            seed: None,
+            waveshape: None,
+            phase: None,
+            zero_align: None,
+            nyquist: None,
+            pan: None,
+            auto_pan: None,
+            filter_envelope: None,
+            fade: None,
+            fade_curve: None,
+            equal_loudness: None,
+            trim_silence: None,
+            pad_to: None,
+            oversample: None,
+            vibrato: None,
+            tremolo: None,
+            offset: None,
+            decay: None,
+            calibration_tone: None,
+            frequency_policy: None,
+            bit_depth: None,
+            bar: None,
+            binaural_beat: None,
+            strict: None,
+        }
+    }
+    pub fn waveform(mut self, waveform: WaveForm) -> Self {
+        self.waveform = Some(waveform);
+        self
+    }
+    pub fn duration(mut self, duration: f64) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+    pub fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = Some(frequency);
+        self
+    }
+    /// Sets the frequency to `note` of `ruler`.
+    pub fn note(self, ruler: &Ruler, note: i64) -> Self {
+        self.frequency(ruler.frequency(note))
+    }
+    /// Sets the duration to `ration` beats at the ruler's tempo, one beat
+    /// being a [`NoteValue::QUARTER`].
+    pub fn length(self, ruler: &Ruler, ration: f64) -> Self {
+        self.duration(NoteValue::QUARTER.seconds(ruler.bpm) * ration)
+    }
+    pub fn amplitude(mut self, amplitude: f64) -> Self {
+        self.amplitude = Some(amplitude);
+        self
+    }
+    pub fn sample_rate(mut self, sample_rate: usize) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+    pub fn envelope(mut self, envelope: Adsr) -> Self {
+        self.envelope = Some(envelope);
+        self
+    }
+    pub fn pitch_envelope(mut self, envelope: Adsr, depth: f64) -> Self {
+        self.pitch_envelope = Some((envelope, depth));
+        self
+    }
+    pub fn unison(mut self, unison: usize, chorus_depth: f64) -> Self {
+        self.unison = Some(unison);
+        self.chorus_depth = Some(chorus_depth);
+        self
+    }
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    pub fn waveshape(mut self, curve: ShapeCurve, drive: f32) -> Self {
+        self.waveshape = Some((curve, drive));
+        self
+    }
+    pub fn phase(mut self, phase: f64) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+    pub fn zero_align(mut self, zero_align: bool) -> Self {
+        self.zero_align = Some(zero_align);
+        self
+    }
+    pub fn nyquist(mut self, nyquist: NyquistPolicy) -> Self {
+        self.nyquist = Some(nyquist);
+        self
+    }
+    pub fn pan(mut self, pan: f64) -> Self {
+        self.pan = Some(pan);
+        self
+    }
+    pub fn auto_pan(mut self, rate: f64, depth: f64) -> Self {
+        self.auto_pan = Some((rate, depth));
+        self
+    }
+    pub fn filter_envelope(mut self, envelope: Adsr, base: f64, depth: f64) -> Self {
+        self.filter_envelope = Some((envelope, base, depth));
+        self
+    }
+    pub fn fade(mut self, fade_in: f64, fade_out: f64) -> Self {
+        self.fade = Some((fade_in, fade_out));
+        self
+    }
+    pub fn fade_curve(mut self, curve: FadeCurve) -> Self {
+        self.fade_curve = Some(curve);
+        self
+    }
+    pub fn equal_loudness(mut self, equal_loudness: bool) -> Self {
+        self.equal_loudness = Some(equal_loudness);
+        self
+    }
+    pub fn trim_silence(mut self, threshold: f32) -> Self {
+        self.trim_silence = Some(threshold);
+        self
+    }
+    pub fn pad_to(mut self, duration: f64) -> Self {
+        self.pad_to = Some(duration);
+        self
+    }
+    pub fn oversample(mut self, factor: usize) -> Self {
+        self.oversample = Some(factor);
+        self
+    }
+    pub fn vibrato(mut self, rate: f64, depth: f64) -> Self {
+        self.vibrato = Some((rate, depth));
+        self
+    }
+    pub fn tremolo(mut self, rate: f64, depth: f64) -> Self {
+        self.tremolo = Some((rate, depth));
+        self
+    }
+    pub fn offset(mut self, offset: f64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    pub fn decay(mut self, tau: f64) -> Self {
+        self.decay = Some(tau);
+        self
+    }
+    pub fn calibration_tone(mut self, calibration_tone: bool) -> Self {
+        self.calibration_tone = Some(calibration_tone);
+        self
+    }
+    pub fn frequency_policy(mut self, policy: FrequencyPolicy) -> Self {
+        self.frequency_policy = Some(policy);
+        self
+    }
+    pub fn bit_depth(mut self, bit_depth: wav::BitDepth) -> Self {
+        self.bit_depth = Some(bit_depth);
+        self
+    }
+    /// Pads renders to whole bars of `signature` at the ruler's tempo; see
+    /// [`Ruler::snap_to_bar`].
+    pub fn snap_to_bar(mut self, ruler: &Ruler, signature: TimeSignature) -> Self {
+        self.bar = Some(signature.bar_seconds(ruler.bpm));
+        self
+    }
+    pub fn binaural_beat(mut self, beat: f64) -> Self {
+        self.binaural_beat = Some(beat);
+        self
+    }
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+    pub fn try_build(self) -> Result<WaveFormer> {
+        let waveformer = self.build();
+        waveformer.validate()?;
+        Ok(waveformer)
+    }
+    pub fn build(self) -> WaveFormer {
+        WaveFormer {
+            waveform: self.waveform.unwrap_or(wave_forms::sin),
+            duration: self.duration.unwrap_or(constants::DEFAULT_DURATION),
+            frequency: self.frequency.unwrap_or(constants::DEFAULT_FREQUENCY),
+            amplitude: self.amplitude.unwrap_or(constants::DEFAULT_AMPLITUDE),
+            sample_rate: self.sample_rate.unwrap_or(constants::FRAME_RATE),
+            envelope: self.envelope,
+            pitch_envelope: self.pitch_envelope.map(|(envelope, _)| envelope),
+            pitch_env_depth: self.pitch_envelope.map_or(0.0, |(_, depth)| depth),
+            unison: self.unison.unwrap_or(1),
+            chorus_depth: self.chorus_depth.unwrap_or(0.0),
+            seed: self.seed.unwrap_or(0),
+            waveshape: self.waveshape,
+            phase: self.phase.unwrap_or(0.0).rem_euclid(1.0),
+            zero_align: self.zero_align.unwrap_or(false),
+            nyquist: self.nyquist.unwrap_or_default(),
+            pan: self.pan.unwrap_or(0.0),
+            auto_pan_rate: self.auto_pan.map_or(0.0, |(rate, _)| rate),
+            auto_pan_depth: self.auto_pan.map_or(0.0, |(_, depth)| depth),
+            filter_envelope: self.filter_envelope,
+            fade_in: self.fade.map_or(0.0, |(fade_in, _)| fade_in),
+            fade_out: self.fade.map_or(0.0, |(_, fade_out)| fade_out),
+            fade_curve: self.fade_curve.unwrap_or_default(),
+            equal_loudness: self.equal_loudness.unwrap_or(false),
+            trim_silence: self.trim_silence,
+            pad_to: self.pad_to,
+            oversample: self.oversample.unwrap_or(1).max(1),
+            vibrato_rate: self.vibrato.map_or(0.0, |(rate, _)| rate),
+            vibrato_depth: self.vibrato.map_or(0.0, |(_, depth)| depth),
+            tremolo_rate: self.tremolo.map_or(0.0, |(rate, _)| rate),
+            tremolo_depth: self.tremolo.map_or(0.0, |(_, depth)| depth),
+            offset: self.offset.unwrap_or(0.0).max(0.0),
+            decay: self.decay,
+            calibration_tone: self.calibration_tone.unwrap_or(false),
+            frequency_policy: self.frequency_policy.unwrap_or_default(),
+            bit_depth: self.bit_depth.unwrap_or_default(),
+            bar: self.bar,
+            binaural_beat: self.binaural_beat,
+            strict: self.strict.unwrap_or(cfg!(feature = "strict")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+    use crate::testing;
+
+    fn frames(samples: &[f32], start: f64, end: f64) -> Vec<f32> {
+        let mono = crate::mix::to_mono(samples, constants::CHANNELS);
+        mono[crate::duration_to_frame(start)..crate::duration_to_frame(end)].to_vec()
+    }
+
+    #[test]
+    fn pitch_envelope_bends_the_attack_by_its_depth() {
+        let samples = WaveFormerBuilder::new()
+            .frequency(440.0)
+            .duration(1.0)
+            .pitch_envelope(Adsr::new(0.0, 0.5, 0.0, 0.0), 12.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let early = analysis::detect_frequency(&frames(&samples, 0.0, 0.01), 48_000).unwrap();
+        let steady = analysis::detect_frequency(&frames(&samples, 0.7, 0.9), 48_000).unwrap();
+        // The envelope has fallen about 1% by the middle of the first window.
+        assert!((analysis::cents(early, steady) - 1188.0).abs() < 30.0);
+        assert!(analysis::cents(steady, 440.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn single_voice_unison_matches_the_plain_render() {
+        let plain = WaveFormerBuilder::new().build().render_to_buffer().unwrap();
+        let unison = WaveFormerBuilder::new()
+            .unison(1, 12.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        assert_eq!(plain, unison);
+    }
+
+    #[test]
+    fn unison_widens_the_stereo_image_reproducibly() {
+        let chorus = || {
+            WaveFormerBuilder::new()
+                .unison(4, 12.0)
+                .seed(7)
+                .build()
+                .render_to_buffer()
+                .unwrap()
+        };
+        let plain = WaveFormerBuilder::new().build().render_to_buffer().unwrap();
+        let wide = chorus();
+        assert!(analysis::stereo_correlation(&plain) > 0.999);
+        assert!(analysis::stereo_correlation(&wide) < 0.9);
+        assert_eq!(wide, chorus());
+    }
+
+    #[test]
+    fn zero_align_starts_and_ends_near_zero() {
+        let samples = WaveFormerBuilder::new()
+            .frequency(437.0)
+            .duration(0.1)
+            .phase(0.3)
+            .zero_align(true)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let mono = crate::mix::to_mono(&samples, constants::CHANNELS);
+        assert!(mono[0].abs() < 1e-6);
+        assert!(mono[mono.len() - 1].abs() < 0.05);
+    }
+
+    fn above_nyquist(policy: NyquistPolicy) -> Result<Vec<f32>> {
+        WaveFormerBuilder::new()
+            .frequency(30_000.0)
+            .duration(0.5)
+            .nyquist(policy)
+            .build()
+            .render_to_buffer()
+    }
+
+    #[test]
+    fn above_nyquist_clamps_instead_of_aliasing() {
+        let samples = above_nyquist(NyquistPolicy::Clamp).unwrap();
+        let mono = crate::mix::to_mono(&samples, constants::CHANNELS);
+        let clamped = 24_000.0 * NYQUIST_CLAMP;
+        let aliased = 48_000.0 - 30_000.0;
+        let power = |frequency| analysis::goertzel_power(&mono, frequency, 48_000);
+        assert!(power(clamped) > 100.0 * power(aliased));
+    }
+
+    #[test]
+    fn above_nyquist_can_render_silence_or_fail() {
+        let silence = above_nyquist(NyquistPolicy::Silence).unwrap();
+        assert_eq!(
+            silence.len(),
+            crate::duration_to_frame(0.5) * constants::CHANNELS
+        );
+        assert!(silence.iter().all(|&sample| sample == 0.0));
+        let error = above_nyquist(NyquistPolicy::Error).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    /// Mean power of one channel over consecutive `window`-frame blocks.
+    fn channel_energy(samples: &[f32], channel: usize, window: usize) -> Vec<f64> {
+        samples
+            .chunks_exact(window * constants::CHANNELS)
+            .map(|block| {
+                block
+                    .iter()
+                    .skip(channel)
+                    .step_by(constants::CHANNELS)
+                    .map(|&sample| (sample as f64).powi(2))
+                    .sum::<f64>()
+                    / window as f64
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_depth_auto_pan_swaps_energy_between_channels() {
+        let samples = WaveFormerBuilder::new()
+            .duration(2.0)
+            .auto_pan(1.0, 1.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let left = channel_energy(&samples, 0, 2_400);
+        let right = channel_energy(&samples, 1, 2_400);
+        let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+        let (left_mean, right_mean) = (mean(&left), mean(&right));
+        let (mut covariance, mut left_power, mut right_power) = (0.0, 0.0, 0.0);
+        for (l, r) in left.iter().zip(&right) {
+            covariance += (l - left_mean) * (r - right_mean);
+            left_power += (l - left_mean).powi(2);
+            right_power += (r - right_mean).powi(2);
+        }
+        assert!(covariance / (left_power * right_power).sqrt() < -0.95);
+        // 50 ms blocks: the LFO is hard right around block 4 and hard left
+        // around block 14.
+        assert!(right[4] > 10.0 * left[4]);
+        assert!(left[14] > 10.0 * right[14]);
+    }
+
+    #[test]
+    fn gain_closure_scales_each_frame() {
+        let waveformer = WaveFormerBuilder::new().duration(0.5).build();
+        let plain = waveformer.render_to_buffer().unwrap();
+        let ramped = waveformer
+            .render_to_buffer_with_gain(|seconds| seconds / 0.5)
+            .unwrap();
+        for (frame, (ramp, dry)) in ramped
+            .chunks_exact(constants::CHANNELS)
+            .zip(plain.chunks_exact(constants::CHANNELS))
+            .enumerate()
+        {
+            let gain = crate::frame_to_duration(frame) / 0.5;
+            for (ramp, dry) in ramp.iter().zip(dry) {
+                assert!((*ramp as f64 - *dry as f64 * gain).abs() < 1e-6);
+            }
+        }
+        let quarter = ramped.len() / 4;
+        let peaks: Vec<f32> = ramped
+            .chunks(quarter)
+            .map(|block| {
+                block
+                    .iter()
+                    .fold(0.0_f32, |peak, sample| peak.max(sample.abs()))
+            })
+            .collect();
+        assert!(peaks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn non_finite_parameters_are_rejected() {
+        let nan = || WaveFormerBuilder::new().frequency(f64::NAN);
+        assert_eq!(
+            nan().try_build().unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        let error = nan().build().render_to_buffer().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        for builder in [
+            WaveFormerBuilder::new().amplitude(f64::INFINITY),
+            WaveFormerBuilder::new().duration(f64::NAN),
+        ] {
+            assert!(builder.try_build().is_err());
+        }
+        let samples = WaveFormerBuilder::new()
+            .try_build()
+            .unwrap()
+            .render_to_buffer()
+            .unwrap();
+        assert!(samples.iter().all(|sample| sample.is_finite()));
+    }
+
+    #[test]
+    fn render_to_writer_emits_a_complete_wav() {
+        let mut waveformer = WaveFormerBuilder::new().duration(0.1).build();
+        let mut bytes = Vec::new();
+        waveformer.render_to_writer(&mut bytes).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        let riff_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        assert_eq!(riff_size as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..12], b"WAVE");
+        let reader = hound::WavReader::new(std::io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(reader.spec(), waveformer.spec());
+        assert_eq!(
+            reader.len() as usize,
+            crate::duration_to_frame(0.1) * constants::CHANNELS
+        );
+    }
+
+    #[test]
+    fn filter_envelope_opens_in_the_attack_and_closes_in_the_release() {
+        let samples = WaveFormerBuilder::new()
+            .waveform(crate::wave_forms::saw)
+            .frequency(110.0)
+            .duration(1.2)
+            .filter_envelope(Adsr::new(0.4, 0.0, 1.0, 0.4), 200.0, 6_000.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let centroid = |start| {
+            let window = frames(&samples, start, start + 0.05);
+            testing::spectral_centroid(&window, 2_048, 48_000)
+        };
+        let attack: Vec<f64> = [0.02, 0.15, 0.3].into_iter().map(centroid).collect();
+        let release: Vec<f64> = [0.85, 1.0, 1.12].into_iter().map(centroid).collect();
+        assert!(
+            attack.windows(2).all(|pair| pair[0] < pair[1]),
+            "{attack:?}"
+        );
+        assert!(
+            release.windows(2).all(|pair| pair[0] > pair[1]),
+            "{release:?}"
+        );
+    }
+
+    #[test]
+    fn frame_count_matches_the_rendered_length() {
+        for (sample_rate, duration) in [(48_000, 1.0), (44_100, 0.3), (96_000, 0.123)] {
+            let waveformer = WaveFormerBuilder::new()
+                .sample_rate(sample_rate)
+                .duration(duration)
+                .build();
+            let samples = waveformer.render_to_buffer().unwrap();
+            assert_eq!(
+                samples.len(),
+                waveformer.frame_count() * constants::CHANNELS
+            );
+            assert_eq!(waveformer.output_frames(), waveformer.frame_count());
+        }
+        for builder in [
+            WaveFormerBuilder::new().duration(0.2).offset(0.1),
+            WaveFormerBuilder::new().duration(0.2).pad_to(0.5),
+            WaveFormerBuilder::new()
+                .duration(0.3)
+                .snap_to_bar(&Ruler::default(), TimeSignature::new(1, 4)),
+            WaveFormerBuilder::new()
+                .duration(0.2)
+                .calibration_tone(true),
+        ] {
+            let waveformer = builder.build();
+            let samples = waveformer.render_to_buffer().unwrap();
+            assert_eq!(
+                samples.len(),
+                waveformer.output_frames() * constants::CHANNELS
+            );
+        }
+    }
+
+    #[test]
+    fn streaming_render_writes_every_frame_in_blocks() {
+        let path = testing::temp_path("streaming.wav");
+        let mut waveformer = WaveFormerBuilder::new().duration(10.0).build();
+        let blocks = waveformer.render_streaming(&path).unwrap();
+        let (samples, _) = wav::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let frames = waveformer.frame_count();
+        assert_eq!(samples.len(), frames * constants::CHANNELS);
+        // The short final block joins the one held back before it.
+        assert_eq!(blocks, frames / RENDER_BLOCK);
+        assert_eq!(samples, waveformer.render_to_buffer().unwrap());
+    }
+
+    #[test]
+    fn note_and_length_read_the_ruler() {
+        let ruler = Ruler::default();
+        let waveformer = WaveFormerBuilder::new()
+            .note(&ruler, 0)
+            .length(&ruler, 1.0)
+            .build();
+        assert_eq!(waveformer.frequency, ruler.frequency(0));
+        assert_eq!(
+            waveformer.duration,
+            crate::NoteValue::QUARTER.seconds(ruler.bpm)
+        );
+        let note = WaveFormerBuilder::new().note(&ruler, 7).build();
+        assert_eq!(note.frequency, ruler.frequency(7));
+        let slower = ruler.clone().with_bpm(60.0).unwrap();
+        let bar = WaveFormerBuilder::new().length(&slower, 4.0).build();
+        assert_eq!(bar.duration, TimeSignature::default().bar_seconds(60.0));
+        // Batch lengths keep the ruler's own rations.
+        assert_eq!(ruler.duration(1.0), 2.0);
+    }
+
+    #[test]
+    fn preview_is_at_most_the_requested_length() {
+        let path = testing::temp_path("preview.wav");
+        for (duration, preview) in [(2.0, 0.25), (0.1, 0.25)] {
+            let mut waveformer = WaveFormerBuilder::new().duration(duration).build();
+            waveformer.render_preview(preview, &path).unwrap();
+            let (samples, _) = wav::read(&path).unwrap();
+            let frames = crate::duration_to_frame(f64::min(duration, preview));
+            assert_eq!(samples.len(), frames * constants::CHANNELS);
+            // The fade leaves no click at the cut.
+            assert!(samples[samples.len() - constants::CHANNELS..]
+                .iter()
+                .all(|sample| sample.abs() < 1e-3));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn loop_points_land_on_rising_zero_crossings() {
+        let path = testing::temp_path("looped.wav");
+        let mut waveformer = WaveFormerBuilder::new()
+            .frequency(437.0)
+            .duration(1.0)
+            .build();
+        let (start, end) = waveformer.render_looped(10_000, 30_000, &path).unwrap();
+        assert_eq!(wav::read_loop_points(&path).unwrap(), Some((start, end)));
+        let (samples, _) = wav::read(&path).unwrap();
+        let mono = crate::mix::to_mono(&samples, constants::CHANNELS);
+        let period = (48_000.0 / 437.0_f64).ceil() as usize;
+        assert!(start.abs_diff(10_000) <= period && end.abs_diff(30_000) <= period);
+        // One sample step of the sine at full scale.
+        let step = (2.0 * PI * 437.0 / 48_000.0) as f32;
+        assert!(mono[start] >= 0.0 && mono[start] < step);
+        assert!(mono[end] < 0.0 && mono[end] > -step);
+        let error = waveformer.render_looped(30_000, 10_000, &path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn gain_array_is_stretched_over_the_render() {
+        let path = testing::temp_path("envelope.wav");
+        let mut waveformer = WaveFormerBuilder::new().duration(1.0).build();
+        waveformer
+            .render_with_envelope(&[0.0, 1.0, 0.5], &path)
+            .unwrap();
+        let (samples, _) = wav::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let plain = waveformer.render_to_buffer().unwrap();
+        let expected = |seconds: f64| {
+            if seconds < 0.5 {
+                2.0 * seconds
+            } else {
+                1.0 - (seconds - 0.5)
+            }
+        };
+        for (frame, (shaped, dry)) in samples
+            .chunks_exact(2)
+            .zip(plain.chunks_exact(2))
+            .enumerate()
+        {
+            let gain = expected(crate::frame_to_duration(frame));
+            assert!((shaped[0] as f64 - dry[0] as f64 * gain).abs() < 1e-6);
+        }
+        assert!(waveformer.render_with_envelope(&[], &path).is_err());
+    }
+
+    #[test]
+    fn raw_pcm_is_headerless_little_endian() {
+        let path = testing::temp_path("raw.pcm");
+        let mut waveformer = WaveFormerBuilder::new().duration(1.0).phase(0.25).build();
+        let samples = waveformer.render_to_buffer().unwrap();
+        for (format, bytes) in [(wav::PcmFormat::F32, 4), (wav::PcmFormat::I16, 2)] {
+            waveformer.render_raw_pcm(&path, format).unwrap();
+            let raw = std::fs::read(&path).unwrap();
+            assert_eq!(raw.len(), 48_000 * constants::CHANNELS * bytes);
+            let first = match format {
+                wav::PcmFormat::F32 => f32::from_le_bytes(raw[0..4].try_into().unwrap()),
+                wav::PcmFormat::I16 => {
+                    i16::from_le_bytes(raw[0..2].try_into().unwrap()) as f32 / i16::MAX as f32
+                }
+            };
+            assert!((first - samples[0]).abs() < 1e-4);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn pad_to_fills_with_silence_or_cuts() {
+        let padded = WaveFormerBuilder::new()
+            .duration(0.3)
+            .pad_to(1.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        assert_eq!(padded.len(), 48_000 * constants::CHANNELS);
+        let tone = crate::duration_to_frame(0.3) * constants::CHANNELS;
+        assert!(padded[..tone].iter().any(|&sample| sample != 0.0));
+        assert!(padded[tone..].iter().all(|&sample| sample == 0.0));
+        let trimmed = WaveFormerBuilder::new()
+            .duration(1.0)
+            .trim_silence(1e-4)
+            .pad_to(0.5)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        assert_eq!(trimmed.len(), 24_000 * constants::CHANNELS);
+    }
+
+    /// Spectral energy of the mono mix above `cutoff` Hz.
+    fn energy_above(samples: &[f32], cutoff: f64) -> f64 {
+        let mono = crate::mix::to_mono(samples, constants::CHANNELS);
+        let spectrum = analysis::magnitude_spectrum(&mono[4_096..], 16_384);
+        let bin = 48_000.0 / 16_384.0;
+        spectrum
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index as f64 * bin > cutoff)
+            .map(|(_, magnitude)| magnitude * magnitude)
+            .sum()
+    }
+
+    #[test]
+    fn oversampling_cuts_aliased_energy() {
+        let render = |oversample| {
+            WaveFormerBuilder::new()
+                .waveform(crate::wave_forms::saw)
+                .frequency(1_234.0)
+                .duration(0.5)
+                .waveshape(ShapeCurve::HardClip, 3.0)
+                .oversample(oversample)
+                .build()
+                .render_to_buffer()
+                .unwrap()
+        };
+        let (plain, oversampled) = (render(1), render(4));
+        assert_eq!(plain.len(), oversampled.len());
+        let (plain, oversampled) = (
+            energy_above(&plain, 12_000.0),
+            energy_above(&oversampled, 12_000.0),
+        );
+        assert!(oversampled < 0.5 * plain, "{oversampled} vs {plain}");
+    }
+
+    #[test]
+    fn quarter_note_tremolo_at_120_bpm_pulses_twice_a_second() {
+        let ruler = Ruler::default();
+        assert_eq!(ruler.rate(crate::NoteValue::QUARTER), 2.0);
+        assert_eq!(crate::NoteValue::EIGHTH.rate(120.0), 4.0);
+        let samples = WaveFormerBuilder::new()
+            .duration(2.0)
+            .tremolo(ruler.rate(crate::NoteValue::QUARTER), 1.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        // 10 ms blocks; the quietest block of each half second is its dip.
+        let energy = channel_energy(&samples, 0, 480);
+        let dips: Vec<usize> = energy
+            .chunks(50)
+            .enumerate()
+            .map(|(half, blocks)| {
+                let quietest = (0..blocks.len())
+                    .min_by(|&a, &b| blocks[a].total_cmp(&blocks[b]))
+                    .unwrap();
+                half * 50 + quietest
+            })
+            .collect();
+        assert_eq!(dips.len(), 4);
+        assert!(
+            dips.windows(2).all(|pair| pair[1] - pair[0] == 50),
+            "{dips:?}"
+        );
+    }
+
+    #[test]
+    fn offset_delays_the_first_sample() {
+        let samples = WaveFormerBuilder::new()
+            .duration(0.2)
+            .phase(0.25)
+            .offset(0.5)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let first = samples.iter().position(|&sample| sample != 0.0).unwrap();
+        assert_eq!(first / constants::CHANNELS, crate::duration_to_frame(0.5));
+        assert_eq!(
+            samples.len(),
+            crate::duration_to_frame(0.7) * constants::CHANNELS
+        );
+    }
+
+    #[test]
+    fn decay_falls_to_one_over_e_at_tau() {
+        let builder = || {
+            WaveFormerBuilder::new()
+                .frequency(100.0)
+                .phase(0.25)
+                .duration(1.0)
+        };
+        let plain = builder().build().render_to_buffer().unwrap();
+        let decayed = builder().decay(0.1).build().render_to_buffer().unwrap();
+        let frame = crate::duration_to_frame(0.1) * constants::CHANNELS;
+        assert!((decayed[frame] / plain[frame] - (-1.0_f32).exp()).abs() < 1e-3);
+        assert!((decayed[0] - plain[0]).abs() < 1e-6);
+        let tail = &decayed[decayed.len() - 4_800..];
+        assert!(tail.iter().all(|sample| sample.abs() < 1e-4));
+    }
+
+    #[test]
+    fn defaults_come_from_the_named_constants() {
+        let waveformer = WaveFormerBuilder::new().build();
+        assert_eq!(waveformer.frequency, constants::DEFAULT_FREQUENCY);
+        assert_eq!(waveformer.duration, constants::DEFAULT_DURATION);
+        assert_eq!(waveformer.amplitude, constants::DEFAULT_AMPLITUDE);
+    }
+
+    #[test]
+    fn calibration_tone_leads_the_original_content() {
+        let builder = || WaveFormerBuilder::new().frequency(440.0).duration(0.5);
+        let plain = builder().build().render_to_buffer().unwrap();
+        let samples = builder()
+            .calibration_tone(true)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let tone = crate::duration_to_frame(CALIBRATION_DURATION) * constants::CHANNELS;
+        assert_eq!(samples.len(), tone + plain.len());
+        assert!(samples[tone..] == plain[..]);
+        let lead = &samples[..tone];
+        assert!((crate::effects::peak(lead) as f64 - CALIBRATION_LEVEL).abs() < 1e-4);
+        let mono = crate::mix::to_mono(lead, constants::CHANNELS);
+        let pitch = analysis::detect_frequency(&mono, constants::FRAME_RATE).unwrap();
+        assert!(analysis::cents(pitch, CALIBRATION_FREQUENCY).abs() < 5.0);
+    }
+
+    #[test]
+    fn f32_render_stays_within_its_documented_drift() {
+        let max_error = |waveform, duration| {
+            let waveformer = WaveFormerBuilder::new()
+                .frequency(440.0)
+                .duration(duration)
+                .waveform(waveform)
+                .build();
+            let (exact, fast) = (
+                waveformer.render_to_buffer().unwrap(),
+                waveformer.render_to_buffer_f32().unwrap(),
+            );
+            assert_eq!(exact.len(), fast.len());
+            let error = exact
+                .iter()
+                .zip(&fast)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f32::max);
+            error as f64 / waveformer.amplitude
+        };
+        for waveform in [wave_forms::sin, wave_forms::tri] {
+            assert!(max_error(waveform, 1.0) < 2e-3);
+            assert!(max_error(waveform, 10.0) < 2e-2);
+        }
+    }
+
+    #[test]
+    fn f32_render_falls_back_beyond_plain_tones() {
+        let waveformer = WaveFormerBuilder::new()
+            .duration(0.2)
+            .envelope(crate::envelope::Adsr::new(0.01, 0.05, 0.5, 0.05))
+            .build();
+        assert!(
+            waveformer.render_to_buffer_f32().unwrap() == waveformer.render_to_buffer().unwrap()
+        );
+    }
+
+    #[test]
+    fn zero_frequency_is_silence_and_negative_runs_backwards() {
+        let render = |frequency, policy| {
+            WaveFormerBuilder::new()
+                .frequency(frequency)
+                .duration(0.1)
+                .waveform(wave_forms::saw)
+                .frequency_policy(policy)
+                .build()
+                .render_to_buffer()
+        };
+        let silent = render(0.0, FrequencyPolicy::Lenient).unwrap();
+        assert_eq!(
+            silent.len(),
+            crate::duration_to_frame(0.1) * constants::CHANNELS
+        );
+        assert!(silent.iter().all(|&sample| sample == 0.0));
+        let forward = render(220.0, FrequencyPolicy::Lenient).unwrap();
+        let backward = render(-220.0, FrequencyPolicy::Lenient).unwrap();
+        // A falling saw mirrored in time rises, so past the first sample
+        // the two are negatives of each other.
+        for (a, b) in forward.iter().zip(&backward).skip(constants::CHANNELS) {
+            assert!((a + b).abs() < 1e-4, "{a} {b}");
+        }
+        for frequency in [0.0, -220.0] {
+            let error = render(frequency, FrequencyPolicy::Strict).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn binaural_beat_detunes_only_the_right_channel() {
+        let samples = WaveFormerBuilder::new()
+            .frequency(220.0)
+            .binaural_beat(10.0)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        let channel = |index: usize| -> Vec<f32> {
+            samples
+                .iter()
+                .skip(index)
+                .step_by(constants::CHANNELS)
+                .copied()
+                .collect()
+        };
+        let rate = constants::FRAME_RATE;
+        for (index, own, other) in [(0, 220.0, 230.0), (1, 230.0, 220.0)] {
+            let channel = channel(index);
+            let pitch = analysis::detect_frequency(&channel, rate).unwrap();
+            assert!(analysis::cents(pitch, own).abs() < 5.0, "{pitch}");
+            let power = |frequency| analysis::goertzel_power(&channel, frequency, rate);
+            assert!(power(own) > 1e4 * power(other));
+        }
+    }
+
+    /// Goes NaN half-way through each cycle: at frame 24_001 of a 1 Hz
+    /// tone at 48 kHz.
+    fn nan_past_half(x: f64) -> f64 {
+        if x > 0.5 + 0.25 / 48_000.0 {
+            f64::NAN
+        } else {
+            x
+        }
+    }
+
+    fn nan_voice() -> WaveFormerBuilder {
+        WaveFormerBuilder::new()
+            .frequency(1.0)
+            .waveform(nan_past_half)
+            .offset(0.1)
+            .calibration_tone(true)
+    }
+
+    #[test]
+    fn strict_renders_fail_at_the_first_non_finite_frame() {
+        let frame = crate::duration_to_frame(CALIBRATION_DURATION) + 4_800 + 24_001;
+        let lenient = nan_voice()
+            .strict(false)
+            .build()
+            .render_to_buffer()
+            .unwrap();
+        assert!(lenient[frame * constants::CHANNELS].is_nan());
+        assert!(lenient[..frame * constants::CHANNELS]
+            .iter()
+            .all(|sample| sample.is_finite()));
+        let strict = nan_voice().strict(true).build();
+        for render in [
+            WaveFormer::render_to_buffer,
+            WaveFormer::render_to_buffer_f32,
+        ] {
+            let error = render(&strict).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(
+                error.to_string().ends_with(&format!("at frame {frame}")),
+                "{error}"
+            );
+        }
+        let error = strict.streaming().unwrap().collect().unwrap_err();
+        assert!(
+            error.to_string().ends_with(&format!("at frame {frame}")),
+            "{error}"
+        );
+    }
+
+    #[cfg(feature = "strict")]
+    #[test]
+    fn strict_feature_makes_every_render_strict() {
+        let waveformer = nan_voice().build();
+        assert!(waveformer.strict);
+        let error = waveformer.render_to_buffer().unwrap_err();
+        let frame = crate::duration_to_frame(CALIBRATION_DURATION) + 4_800 + 24_001;
+        assert!(
+            error.to_string().ends_with(&format!("at frame {frame}")),
+            "{error}"
+        );
+        assert!(!nan_voice().strict(false).build().strict);
+    }
+
+    #[test]
+    fn estimated_bytes_match_the_written_file() {
+        let path = testing::temp_path("estimate.wav");
+        for bit_depth in [
+            wav::BitDepth::Int16,
+            wav::BitDepth::Int24,
+            wav::BitDepth::Float32,
+        ] {
+            let mut waveformer = WaveFormerBuilder::new().bit_depth(bit_depth).build();
+            waveformer.render(&path).unwrap();
+            let size = std::fs::metadata(&path).unwrap().len() as usize;
+            assert_eq!(waveformer.estimated_bytes(), size, "{bit_depth:?}");
+        }
+        std::fs::remove_file(&path).unwrap();
+        let sixteen = WaveFormerBuilder::new()
+            .bit_depth(wav::BitDepth::Int16)
+            .build();
+        assert_eq!(
+            sixteen.estimated_bytes(),
+            48_000 * 2 * 2 + wav::WAV_HEADER_SIZE
+        );
+    }
+}
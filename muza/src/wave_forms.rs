@@ -0,0 +1,232 @@
+use std::{
+    f64::consts::PI,
+    fs::File,
+    io::{BufWriter, Result, Write},
+    path::Path,
+};
+
+use crate::WaveForm;
+
+pub fn sqr(x: f64) -> f64 {
+    if x < 0.5 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+pub fn saw(x: f64) -> f64 {
+    1.0 - 2.0 * x
+}
+
+pub fn tri(x: f64) -> f64 {
+    if x < 0.25 {
+        return 4.0 * x;
+    }
+    if x < 0.75 {
+        return 2.0 - 4.0 * x;
+    }
+    4.0 * x - 4.0
+}
+
+pub fn sin(x: f64) -> f64 {
+    (2.0 * PI * x).sin()
+}
+
+/// Sum of sine harmonics, `amplitudes[k]` scaling harmonic `k + 1`, all
+/// starting in sine phase. A closure rather than a [`WaveForm`], which
+/// can't capture the table; render it with
+/// [`WaveFormer::render_to_buffer_with_waveform`](crate::WaveFormer::render_to_buffer_with_waveform).
+pub fn additive(amplitudes: Vec<f64>) -> impl Fn(f64) -> f64 {
+    additive_with_phase(
+        amplitudes
+            .into_iter()
+            .map(|amplitude| (amplitude, 0.0))
+            .collect(),
+    )
+}
+
+/// Like [`additive`] with a starting phase, in cycles, per harmonic:
+/// `partials[k] = (amplitude, phase)` for harmonic `k + 1`. Equal
+/// amplitudes with different phases share a magnitude spectrum but not a
+/// shape, e.g. peakier or flatter waves.
+pub fn additive_with_phase(partials: Vec<(f64, f64)>) -> impl Fn(f64) -> f64 {
+    move |x| {
+        partials
+            .iter()
+            .enumerate()
+            .map(|(index, &(amplitude, phase))| {
+                amplitude * (2.0 * PI * ((index + 1) as f64 * x + phase)).sin()
+            })
+            .sum()
+    }
+}
+
+fn sin_f32(x: f32) -> f32 {
+    (2.0 * std::f32::consts::PI * x).sin()
+}
+
+fn saw_f32(x: f32) -> f32 {
+    1.0 - 2.0 * x
+}
+
+fn sqr_f32(x: f32) -> f32 {
+    if x < 0.5 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn tri_f32(x: f32) -> f32 {
+    if x < 0.25 {
+        return 4.0 * x;
+    }
+    if x < 0.75 {
+        return 2.0 - 4.0 * x;
+    }
+    4.0 * x - 4.0
+}
+
+/// A waveform evaluated in single precision.
+type SingleWaveForm = fn(x: f32) -> f32;
+
+const SINGLE_PRECISION: &[(WaveForm, SingleWaveForm)] = &[
+    (sin, sin_f32),
+    (saw, saw_f32),
+    (sqr, sqr_f32),
+    (tri, tri_f32),
+];
+
+/// `f32` twin of a built-in waveform, `None` for any other.
+pub(crate) fn single_precision(waveform: WaveForm) -> Option<SingleWaveForm> {
+    SINGLE_PRECISION
+        .iter()
+        .find(|&&(known, _)| std::ptr::fn_addr_eq(known, waveform))
+        .map(|&(_, twin)| twin)
+}
+
+const REGISTRY: &[(&str, WaveForm)] = &[("sin", sin), ("saw", saw), ("sqr", sqr), ("tri", tri)];
+
+/// Every built-in waveform with its name, in menu order.
+pub fn all() -> &'static [(&'static str, WaveForm)] {
+    REGISTRY
+}
+
+/// Looks up a built-in waveform by its function name.
+pub fn by_name(name: &str) -> Option<WaveForm> {
+    REGISTRY
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, waveform)| waveform)
+}
+
+/// Reverse of [`by_name`]; `None` for waveforms outside the registry.
+pub fn name_of(waveform: WaveForm) -> Option<&'static str> {
+    REGISTRY
+        .iter()
+        .find(|&&(_, known)| std::ptr::fn_addr_eq(known, waveform))
+        .map(|&(name, _)| name)
+}
+
+/// Writes one cycle of `waveform` as `phase,value` CSV rows, sampled at
+/// `points` evenly spaced phases in `[0, 1)`, for plotting.
+pub fn dump_waveform_csv<S: AsRef<Path>>(
+    waveform: &WaveForm,
+    points: usize,
+    path: S,
+) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "phase,value")?;
+    for point in 0..points {
+        let phase = point as f64 / points as f64;
+        writeln!(writer, "{},{}", phase, waveform(phase))?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_name_round_trips() {
+        for name in ["sin", "saw", "sqr", "tri"] {
+            let waveform = by_name(name).unwrap();
+            assert_eq!(name_of(waveform), Some(name));
+        }
+    }
+
+    #[test]
+    fn unknown_names_are_none() {
+        assert!(by_name("wobble").is_none());
+        fn silence(_: f64) -> f64 {
+            0.0
+        }
+        assert!(name_of(silence).is_none());
+    }
+
+    #[test]
+    fn all_lists_sin_and_round_trips() {
+        assert!(!all().is_empty());
+        assert!(all().iter().any(|&(name, _)| name == "sin"));
+        for &(name, waveform) in all() {
+            assert!(std::ptr::fn_addr_eq(by_name(name).unwrap(), waveform));
+        }
+    }
+
+    #[test]
+    fn csv_dump_has_one_row_per_point() {
+        let path = crate::testing::temp_path("sin.csv");
+        dump_waveform_csv(&(sin as WaveForm), 8, &path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("phase,value"));
+        let rows: Vec<(f64, f64)> = lines
+            .map(|line| {
+                let (phase, value) = line.split_once(',').unwrap();
+                (phase.parse().unwrap(), value.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(rows.len(), 8);
+        assert!(rows.iter().all(|&(_, value)| (-1.0..=1.0).contains(&value)));
+        assert_eq!(rows[2], (0.25, 1.0));
+    }
+
+    #[test]
+    fn partial_phases_change_the_shape_but_not_the_spectrum() {
+        let amplitudes = [1.0, 0.0, 1.0 / 3.0, 0.0, 1.0 / 5.0];
+        let voice = crate::WaveFormerBuilder::new()
+            .frequency(375.0)
+            .duration(0.256)
+            .build();
+        let render = |phases: [f64; 5]| {
+            let partials = amplitudes.iter().copied().zip(phases).collect();
+            voice
+                .render_to_buffer_with_waveform(additive_with_phase(partials))
+                .unwrap()
+        };
+        let sine_phase = render([0.0; 5]);
+        let shifted = render([0.0, 0.0, 0.25, 0.0, 0.5]);
+        let crest = |samples: &[f32]| {
+            let rms = (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>()
+                / samples.len() as f64)
+                .sqrt();
+            crate::effects::peak(samples) as f64 / rms
+        };
+        assert!((crest(&sine_phase) - crest(&shifted)).abs() > 0.1);
+        let spectrum = |samples: &[f32]| {
+            let mono = crate::mix::to_mono(samples, crate::constants::CHANNELS);
+            crate::analysis::magnitude_spectrum(&mono, 8_192)
+        };
+        // 375 Hz lands on bin 64, so every partial sits on a bin.
+        let (a, b) = (spectrum(&sine_phase), spectrum(&shifted));
+        for bin in [64, 192, 320] {
+            assert!((a[bin] / b[bin] - 1.0).abs() < 1e-3, "bin {bin}");
+        }
+        assert!(
+            voice.render_to_buffer_with_waveform(sin).unwrap() == voice.render_to_buffer().unwrap()
+        );
+    }
+}